@@ -0,0 +1,42 @@
+#!/bin/bash
+#
+# Benchmark the memory use of running `.[] | .id` on a large top-level array,
+# read from a file and from standard input,
+# with jq implementations passed as arguments
+#
+# The number of array elements can be set via the environment variable `N`;
+# the default yields an array of about 2 GB.
+# The output shows the maximum resident set size in kilobytes.
+#
+# Example usage, comparing jaq with jq:
+#
+#     cargo build --release
+#     ./bench-memory.sh target/release/jaq jq
+
+TIME=${TIME:-/usr/bin/time -f %M}
+N=${N:-40000000}
+DIR=$(mktemp -d)
+trap 'rm -r $DIR' EXIT
+
+# generate the array element by element, so that the generator needs little memory
+{
+  echo '['
+  $1 -nc "range($N) | {id: ., name: \"item \(.)\", tags: [\"a\", \"b\"]}" | sed '$!s/$/,/'
+  echo ']'
+} > $DIR/array.json
+
+echo -n '{"name": "array-file", "n": '$N', "rss": {'
+for j in $@; do
+  m=$($TIME $j '.[] | .id' $DIR/array.json 2>&1 > /dev/null)
+  [ $j != $1 ] && echo -n ', '
+  echo -n '"'$j'": ['$m']'
+done
+echo '}}'
+
+echo -n '{"name": "array-stdin", "n": '$N', "rss": {'
+for j in $@; do
+  m=$($TIME $j '.[] | .id' < $DIR/array.json 2>&1 > /dev/null)
+  [ $j != $1 ] && echo -n ', '
+  echo -n '"'$j'": ['$m']'
+done
+echo '}}'
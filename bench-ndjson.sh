@@ -0,0 +1,39 @@
+#!/bin/bash
+#
+# Benchmark reading newline-delimited JSON from a file and from standard input
+# with jq implementations passed as arguments
+#
+# The number of generated values can be set via the environment variable `N`.
+#
+# Example usage, comparing jaq with and without the `simd-json` feature:
+#
+#     cargo build --release && cp target/release/jaq jaq-default
+#     cargo build --release --features simd-json && cp target/release/jaq jaq-simd
+#     ./bench-ndjson.sh ./jaq-default ./jaq-simd
+
+TIME='/usr/bin/time -f %U'
+N=${N:-1000000}
+DIR=$(mktemp -d)
+trap 'rm -r $DIR' EXIT
+
+# objects with integers only, and objects with a fractional number
+$1 -nc "range($N) | {id: ., name: \"item \(.)\", tags: [\"a\", \"b\"], pos: {x: ., y: -.}}" > $DIR/ints.json
+$1 -nc "range($N) | {id: ., name: \"item \(.)\", score: (. / 8)}" > $DIR/floats.json
+
+for b in ints floats; do
+  echo -n '{"name": "'$b'-file", "n": '$N', "time": {'
+  for j in $@; do
+    t=$($TIME $j .id $DIR/$b.json 2>&1 > /dev/null)
+    [ $j != $1 ] && echo -n ', '
+    echo -n '"'$j'": ['$t']'
+  done
+  echo '}}'
+
+  echo -n '{"name": "'$b'-stdin", "n": '$N', "time": {'
+  for j in $@; do
+    t=$($TIME $j .id < $DIR/$b.json 2>&1 > /dev/null)
+    [ $j != $1 ] && echo -n ', '
+    echo -n '"'$j'": ['$t']'
+  done
+  echo '}}'
+done
@@ -0,0 +1,78 @@
+//! Base16 (hexadecimal) and base32 encodings of bytes, as specified in RFC 4648.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use jaq_interpret::Error;
+
+const BASE16: &[u8; 16] = b"0123456789abcdef";
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Encode bytes as lowercase hexadecimal digits.
+pub fn base16(bytes: &[u8]) -> String {
+    let digit = |d: u8| char::from(BASE16[usize::from(d)]);
+    bytes
+        .iter()
+        .flat_map(|b| [digit(b >> 4), digit(b & 15)])
+        .collect()
+}
+
+/// Decode pairs of hexadecimal digits, which may be lowercase or uppercase.
+pub fn base16d(s: &str) -> Result<Vec<u8>, Error> {
+    let err = || Error::str(format_args!("cannot decode {s:?} as base16"));
+    let digit = |d: u8| char::from(d).to_digit(16);
+    if s.len() % 2 != 0 {
+        return Err(err());
+    }
+    let byte = |c: &[u8]| Some((digit(c[0])? << 4 | digit(c[1])?) as u8);
+    s.as_bytes()
+        .chunks(2)
+        .map(byte)
+        .collect::<Option<_>>()
+        .ok_or_else(err)
+}
+
+/// Encode bytes with the base32 alphabet, padding the output with `=`.
+pub fn base32(bytes: &[u8]) -> String {
+    let mut s = String::new();
+    for chunk in bytes.chunks(5) {
+        // every chunk of up to 40 bits yields up to 8 characters of 5 bits each
+        let n = (0..5).fold(0u64, |n, i| n << 8 | u64::from(*chunk.get(i).unwrap_or(&0)));
+        let chars = (chunk.len() * 8 + 4) / 5;
+        let digit = |i| char::from(BASE32[(n >> (35 - 5 * i)) as usize & 31]);
+        s.extend((0..chars).map(digit));
+        s.extend(core::iter::repeat('=').take(8 - chars));
+    }
+    s
+}
+
+/// Decode base32 with padding, failing on invalid characters or padding.
+pub fn base32d(s: &str) -> Result<Vec<u8>, Error> {
+    let err = || Error::str(format_args!("cannot decode {s:?} as base32"));
+    if s.len() % 8 != 0 {
+        return Err(err());
+    }
+    let chunks = s.as_bytes().chunks(8);
+    let last = chunks.len().saturating_sub(1);
+    let mut bytes = Vec::new();
+    for (i, chunk) in chunks.enumerate() {
+        let chars = chunk.iter().take_while(|c| **c != b'=').count();
+        let len = chars * 5 / 8;
+        // padding may only occur at the end and must not hide a whole byte
+        let pad_ok = chars == 8 || (i == last && [2, 4, 5, 7].contains(&chars));
+        if !pad_ok || chunk[chars..].iter().any(|c| *c != b'=') {
+            return Err(err());
+        }
+        let value = |c| BASE32.iter().position(|b| b == c).map(|d| d as u64);
+        let n = chunk[..chars]
+            .iter()
+            .try_fold(0, |n, c| Some(n << 5 | value(c)?))
+            .ok_or_else(err)?;
+        let n = n << (5 * (8 - chars));
+        // bits that do not belong to any byte must be zero
+        if n & ((1 << (40 - 8 * len)) - 1) != 0 {
+            return Err(err());
+        }
+        bytes.extend((0..len).map(|i| (n >> (32 - 8 * i)) as u8));
+    }
+    Ok(bytes)
+}
@@ -7,8 +7,11 @@ extern crate alloc;
 #[cfg(feature = "std")]
 extern crate std;
 
+#[cfg(feature = "format")]
+mod base;
 #[cfg(feature = "math")]
 mod math;
+mod path;
 #[cfg(feature = "regex")]
 mod regex;
 #[cfg(feature = "time")]
@@ -17,7 +20,7 @@ mod time;
 use alloc::string::{String, ToString};
 use alloc::{borrow::ToOwned, boxed::Box, format, rc::Rc, vec::Vec};
 use jaq_interpret::results::{box_once, run_if_ok, then};
-use jaq_interpret::{Error, FilterT, Native, RunPtr, UpdatePtr, Val, ValR, ValRs};
+use jaq_interpret::{Error, FilterT, Native, PathsPtr, RunPtr, UpdatePtr, Val, ValR, ValRs};
 
 /// Return the minimal set of named filters available in jaq
 /// which are implemented as native filters, such as `length`, `keys`, ...,
@@ -25,7 +28,7 @@ use jaq_interpret::{Error, FilterT, Native, RunPtr, UpdatePtr, Val, ValR, ValRs}
 ///
 /// Does not return filters from the standard library, such as `map`.
 pub fn minimal() -> impl Iterator<Item = (String, usize, Native)> {
-    run(CORE_RUN).chain(upd(CORE_UPDATE))
+    run(CORE_RUN).chain(paths(CORE_PATHS))
 }
 
 /// Return those named filters available by default in jaq
@@ -66,6 +69,15 @@ fn upd<'a>(
     })
 }
 
+fn paths<'a>(
+    fs: &'a [(&str, usize, RunPtr, PathsPtr)],
+) -> impl Iterator<Item = (String, usize, Native)> + 'a {
+    fs.iter().map(|&(name, arity, run, paths)| {
+        let f = Native::new(run).with_paths(paths);
+        (name.to_string(), arity, f)
+    })
+}
+
 // This might be included in the Rust standard library:
 // <https://github.com/rust-lang/rust/issues/93610>
 fn rc_unwrap_or_clone<T: Clone>(a: Rc<T>) -> T {
@@ -254,6 +266,37 @@ fn indices<'a>(x: &'a Val, y: &'a Val) -> Result<Box<dyn Iterator<Item = usize>
     }
 }
 
+/// Return true if `x` contains `y`, failing if `x` and `y` have different types.
+fn contains(x: &Val, y: &Val) -> Result<bool, Error> {
+    let num = |v: &Val| matches!(v, Val::Int(_) | Val::Float(_) | Val::Num(_));
+    let same_type = match (x, y) {
+        (Val::Null, Val::Null) | (Val::Bool(_), Val::Bool(_)) => true,
+        (Val::Str(_), Val::Str(_)) | (Val::Arr(_), Val::Arr(_)) | (Val::Obj(_), Val::Obj(_)) => {
+            true
+        }
+        _ => num(x) && num(y),
+    };
+    if same_type {
+        Ok(x.contains(y))
+    } else {
+        Err(Error::str(format_args!(
+            "cannot check whether {x} contains {y}"
+        )))
+    }
+}
+
+/// Return the index of the first occurrence of `x` in the sorted array `xs` if it exists,
+/// otherwise `-i - 1` if `x` would have to be inserted at index `i` to keep `xs` sorted.
+fn bsearch(xs: &[Val], x: &Val) -> isize {
+    let i = xs.partition_point(|y| y < x);
+    let i_int = isize::try_from(i).unwrap();
+    if xs.get(i) == Some(x) {
+        i_int
+    } else {
+        -i_int - 1
+    }
+}
+
 fn once_with<'a, T>(f: impl FnOnce() -> T + 'a) -> Box<dyn Iterator<Item = T> + 'a> {
     Box::new(core::iter::once_with(f))
 }
@@ -278,6 +321,13 @@ const CORE_RUN: &[(&str, usize, RunPtr)] = &[
     ("tojson", 0, |_, cv| {
         once_with(move || Ok(Val::str(cv.1.to_string())))
     }),
+    // like `tojson`, but strings are returned unchanged
+    ("tostring", 0, |_, cv| {
+        once_with(move || match cv.1 {
+            Val::Str(_) => Ok(cv.1),
+            v => Ok(Val::str(v.to_string())),
+        })
+    }),
     ("utf8bytelength", 0, |_, cv| {
         once_with(move || cv.1.as_str().map(|s| Val::Int(s.len() as isize)))
     }),
@@ -294,7 +344,11 @@ const CORE_RUN: &[(&str, usize, RunPtr)] = &[
         once_with(move || cv.1.mutate_str(|s| s.make_ascii_uppercase()))
     }),
     ("reverse", 0, |_, cv| {
-        once_with(move || cv.1.mutate_arr(|a| a.reverse()))
+        once_with(move || match cv.1 {
+            Val::Str(s) => Ok(Val::str(s.chars().rev().collect())),
+            Val::Null => Ok(Val::Null),
+            v => v.mutate_arr(|a| a.reverse()),
+        })
     }),
     ("sort", 0, |_, cv| {
         once_with(move || cv.1.mutate_arr(|a| a.sort()))
@@ -324,20 +378,32 @@ const CORE_RUN: &[(&str, usize, RunPtr)] = &[
     }),
     ("contains", 1, |args, cv| {
         let vals = args.get(0).run(cv.clone());
-        Box::new(vals.map(move |y| Ok(Val::Bool(cv.1.contains(&y?)))))
+        Box::new(vals.map(move |y| contains(&cv.1, &y?).map(Val::Bool)))
+    }),
+    ("bsearch", 1, |args, cv| {
+        let xs = args.get(0).run(cv.clone());
+        Box::new(xs.map(move |x| Ok(Val::Int(bsearch(cv.1.as_arr()?, &x?)))))
     }),
     ("indices", 1, |args, cv| {
         let vals = args.get(0).run(cv.clone());
+        // like `null | .[x]`, this yields `null`
+        if cv.1 == Val::Null {
+            return Box::new(vals.map(|v| v.map(|_| Val::Null)));
+        }
         let to_int = |i: usize| Val::Int(i.try_into().unwrap());
         let f = move |v| indices(&cv.1, &v?).map(|idxs| Val::arr(idxs.map(to_int).collect()));
         Box::new(vals.map(f))
     }),
-    ("first", 1, |args, cv| Box::new(args.get(0).run(cv).take(1))),
-    ("limit", 2, |args, cv| {
-        let n = args.get(0).run(cv.clone()).map(|n| n?.as_int());
-        let f = move |n| args.get(1).run(cv.clone()).take(n);
-        let pos = |n: isize| n.try_into().unwrap_or(0usize);
-        Box::new(n.flat_map(move |n| then(n, |n| Box::new(f(pos(n))))))
+    ("path", 1, |args, cv| args.get(0).path(cv)),
+    ("delpaths", 1, |args, cv| {
+        let paths = args.get(0).run(cv.clone());
+        let f = move |ps: Val| path::delpaths(cv.1.clone(), rc_unwrap_or_clone(ps.into_arr()?));
+        Box::new(paths.map(move |ps| f(ps?)))
+    }),
+    ("setpath", 2, |args, cv| {
+        let (paths, xs) = (args.get(0), args.get(1));
+        let pxs = paths.cartesian(xs, (cv.0, cv.1.clone()));
+        Box::new(pxs.map(move |(p, x)| path::setpath(cv.1.clone(), &p?.into_arr()?, x?)))
     }),
     ("range", 3, |args, cv| {
         let (from, to, by) = (args.get(0), args.get(1), args.get(2));
@@ -436,6 +502,12 @@ fn to_tsv(vs: &[Val]) -> Result<String, Error> {
     Ok(vs.iter().map(fr).collect::<Result<Vec<_>, _>>()?.join("\t"))
 }
 
+#[cfg(feature = "format")]
+/// Convert decoded bytes to a string, failing if they are not valid UTF-8.
+fn from_utf8(bytes: Vec<u8>) -> ValR {
+    String::from_utf8(bytes).map(Val::str).map_err(Error::str)
+}
+
 #[cfg(feature = "format")]
 const FORMAT: &[(&str, usize, RunPtr)] = &[
     ("@tsv", 0, |_, cv| {
@@ -460,13 +532,21 @@ const FORMAT: &[(&str, usize, RunPtr)] = &[
             STANDARD
                 .decode(cv.1.to_string_or_clone())
                 .map_err(Error::str)
-                .and_then(|d| {
-                    std::str::from_utf8(&d)
-                        .map_err(Error::str)
-                        .map(|s| Val::str(s.to_owned()))
-                })
+                .and_then(from_utf8)
         })
     }),
+    ("@base32", 0, |_, cv| {
+        once_with(move || Ok(Val::str(base::base32(cv.1.to_string_or_clone().as_bytes()))))
+    }),
+    ("@base32d", 0, |_, cv| {
+        once_with(move || base::base32d(&cv.1.to_string_or_clone()).and_then(from_utf8))
+    }),
+    ("@base16", 0, |_, cv| {
+        once_with(move || Ok(Val::str(base::base16(cv.1.to_string_or_clone().as_bytes()))))
+    }),
+    ("@base16d", 0, |_, cv| {
+        once_with(move || base::base16d(&cv.1.to_string_or_clone()).and_then(from_utf8))
+    }),
 ];
 
 #[cfg(feature = "math")]
@@ -569,33 +649,109 @@ const TIME: &[(&str, usize, RunPtr)] = &[
     ("todateiso8601", 0, |_, cv| {
         once_with(move || time::to_iso8601(&cv.1).map(Val::str))
     }),
+    ("gmtime", 0, |_, cv| once_with(move || time::gmtime(&cv.1))),
+    ("localtime", 0, |_, cv| {
+        once_with(move || time::localtime(&cv.1))
+    }),
+    ("mktime", 0, |_, cv| once_with(move || time::mktime(&cv.1))),
+    ("strftime", 1, |args, cv| {
+        let fmts = args.get(0).run(cv.clone());
+        Box::new(fmts.map(move |fmt| Ok(Val::str(time::strftime(&cv.1, &fmt?.to_str()?)?))))
+    }),
+    ("strptime", 1, |args, cv| {
+        let fmts = args.get(0).run(cv.clone());
+        Box::new(fmts.map(move |fmt| time::strptime(cv.1.as_str()?, &fmt?.to_str()?)))
+    }),
 ];
 
-const CORE_UPDATE: &[(&str, usize, RunPtr, UpdatePtr)] = &[
+/// Filters that can be used as paths, such as `first(f)` in `path(first(f))`.
+///
+/// Updates with these filters, such as `first(f) |= g`, use their paths.
+const CORE_PATHS: &[(&str, usize, RunPtr, PathsPtr)] = &[
     (
         "empty",
         0,
         |_, _| Box::new(core::iter::empty()),
-        |_, cv, _| box_once(Ok(cv.1)),
+        |_, _| Box::new(core::iter::empty()),
     ),
     (
         "error",
         0,
         |_, cv| box_once(Err(Error::Val(cv.1))),
-        |_, cv, _| box_once(Err(Error::Val(cv.1))),
+        |_, cv| box_once(Err(Error::Val(cv.1 .0))),
+    ),
+    (
+        "getpath",
+        1,
+        |args, cv| {
+            let paths = args.get(0).run(cv.clone());
+            Box::new(paths.map(move |p| path::getpath(&cv.1, &p?.into_arr()?)))
+        },
+        |args, cv| {
+            let paths = args.get(0).run((cv.0.clone(), cv.1 .0.clone()));
+            Box::new(paths.map(move |path| {
+                let path = path?.into_arr()?;
+                let (v, p) = cv.1.clone();
+                let p = path.iter().fold(p, |p, k| p.cons(k.clone()));
+                Ok((path::getpath(&v, &path)?, p))
+            }))
+        },
+    ),
+    (
+        "first",
+        1,
+        |args, cv| Box::new(args.get(0).run(cv).take(1)),
+        |args, cv| Box::new(args.get(0).paths(cv).take(1)),
+    ),
+    (
+        "limit",
+        2,
+        |args, cv| {
+            let n = args.get(0).run(cv.clone()).map(|n| n?.as_int());
+            let f = move |n| args.get(1).run(cv.clone()).take(n);
+            Box::new(n.flat_map(move |n| then(n, |n| Box::new(f(pos(n))))))
+        },
+        |args, cv| {
+            let n = args.get(0).run((cv.0.clone(), cv.1 .0.clone()));
+            let f = move |n| args.get(1).paths(cv.clone()).take(n);
+            Box::new(n.flat_map(move |n| then(n.and_then(|n| n.as_int()), |n| Box::new(f(pos(n))))))
+        },
     ),
 ];
 
+/// Convert a number of outputs to take, treating negative numbers as 0.
+fn pos(n: isize) -> usize {
+    n.try_into().unwrap_or(0)
+}
+
 #[cfg(feature = "log")]
 fn debug<T: core::fmt::Display>(x: T) -> T {
-    log::debug!("{}", x);
+    log::debug!("[\"DEBUG:\",{}]", x);
     x
 }
 
+/// Log a value at the `info` level, printing strings without quotes.
 #[cfg(feature = "log")]
-const LOG: &[(&str, usize, RunPtr, UpdatePtr)] = &[(
-    "debug",
-    0,
-    |_, cv| once_with(move || Ok(debug(cv.1))),
-    |_, cv, f| f(debug(cv.1)),
-)];
+fn stderr(v: Val) -> Val {
+    match &v {
+        Val::Str(s) => log::info!("{}", s),
+        v => log::info!("{}", v),
+    }
+    v
+}
+
+#[cfg(feature = "log")]
+const LOG: &[(&str, usize, RunPtr, UpdatePtr)] = &[
+    (
+        "debug",
+        0,
+        |_, cv| once_with(move || Ok(debug(cv.1))),
+        |_, cv, f| f(debug(cv.1)),
+    ),
+    (
+        "stderr",
+        0,
+        |_, cv| once_with(move || Ok(stderr(cv.1))),
+        |_, cv, f| f(stderr(cv.1)),
+    ),
+];
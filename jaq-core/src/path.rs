@@ -0,0 +1,190 @@
+//! Access to values at paths given as arrays, as returned by `path(f)`.
+//!
+//! Unlike indexing via `.[k]`, these functions treat `null` like
+//! an empty array or object, as jq does.
+
+use alloc::{rc::Rc, vec::Vec};
+use core::ops::Range;
+use jaq_interpret::{Error, Val, ValR, ValT};
+
+/// Return the value at the given path, or `null` if it does not exist.
+pub fn getpath(v: &Val, path: &[Val]) -> ValR {
+    path.iter().try_fold(v.clone(), |v, k| match (v, k) {
+        (Val::Null, _) => Ok(Val::Null),
+        (v @ Val::Arr(_), k @ Val::Obj(_)) => {
+            let (from, upto) = (bound(k, "start")?, bound(k, "end")?);
+            v.range(from.as_ref()..upto.as_ref())
+        }
+        (v, k) => v.index(k),
+    })
+}
+
+/// Replace the value at the given path by `x`, creating missing arrays and objects.
+///
+/// Arrays are padded with `null` when setting an index beyond their length.
+pub fn setpath(v: Val, path: &[Val], x: Val) -> ValR {
+    let (k, rest) = match path.split_first() {
+        Some(kr) => kr,
+        None => return Ok(x),
+    };
+    match (v, k) {
+        (Val::Null, Val::Str(_)) => setpath(Val::obj(Default::default()), path, x),
+        (Val::Null, Val::Int(_) | Val::Obj(_)) => setpath(Val::arr(Vec::new()), path, x),
+        (Val::Obj(mut o), Val::Str(k)) => {
+            let o_ = Rc::make_mut(&mut o);
+            let y = o_.get_mut(k).map_or(Val::Null, take);
+            o_.insert(k.clone(), setpath(y, rest, x)?);
+            Ok(Val::Obj(o))
+        }
+        (Val::Arr(mut a), Val::Int(i)) => {
+            let a_ = Rc::make_mut(&mut a);
+            let abs = if *i < 0 { a_.len() as isize + i } else { *i };
+            let abs = usize::try_from(abs).map_err(|_| Error::IndexOutOfBounds(*i))?;
+            if abs >= a_.len() {
+                a_.resize(abs + 1, Val::Null);
+            }
+            a_[abs] = setpath(take(&mut a_[abs]), rest, x)?;
+            Ok(Val::Arr(a))
+        }
+        (Val::Arr(mut a), k @ Val::Obj(_)) => {
+            let a_ = Rc::make_mut(&mut a);
+            let (skip, n) = slice(k, a_.len())?;
+            let slice = a_[skip..skip + n].iter().cloned().collect();
+            let y = setpath(slice, rest, x)?.into_arr()?;
+            a_.splice(skip..skip + n, y.iter().cloned());
+            Ok(Val::Arr(a))
+        }
+        (v, k) => Err(Error::Index(v, k.clone())),
+    }
+}
+
+/// Remove the values at the given paths.
+///
+/// Like in jq, all paths refer to the original value;
+/// that is, removing a value does not change what the other paths refer to.
+pub fn delpaths(v: Val, paths: Vec<Val>) -> ValR {
+    let paths = paths
+        .into_iter()
+        .map(|p| p.into_arr())
+        .collect::<Result<Vec<_>, _>>()?;
+    del(v, paths.iter().map(|p| &p[..]).collect())
+}
+
+fn del(v: Val, paths: Vec<&[Val]>) -> ValR {
+    if paths.iter().any(|p| p.is_empty()) {
+        return Ok(Val::Null);
+    }
+    match v {
+        Val::Null => Ok(Val::Null),
+        Val::Obj(mut o) => {
+            let mut keyed = Vec::new();
+            for p in paths {
+                match &p[0] {
+                    Val::Str(k) => keyed.push((k, &p[1..])),
+                    k => return Err(Error::Index(Val::Obj(o), k.clone())),
+                }
+            }
+            keyed.sort();
+            let o_ = Rc::make_mut(&mut o);
+            let mut keyed = keyed.into_iter().peekable();
+            while let Some((k, rest)) = keyed.next() {
+                let mut rests = Vec::from([rest]);
+                while let Some((_, r)) = keyed.next_if(|(l, _)| *l == k) {
+                    rests.push(r);
+                }
+                if rests.iter().any(|r| r.is_empty()) {
+                    o_.shift_remove(k);
+                } else if let Some(y) = o_.get_mut(k) {
+                    *y = del(take(y), rests)?;
+                }
+            }
+            Ok(Val::Obj(o))
+        }
+        Val::Arr(mut a) => {
+            let (mut dels, mut nested) = (Vec::new(), Vec::new());
+            for p in paths {
+                match resolve(&a, p)? {
+                    (range, []) => dels.extend(range),
+                    (range, rest) => nested.push((range.start, rest)),
+                }
+            }
+            let a_ = Rc::make_mut(&mut a);
+
+            nested.sort();
+            let mut nested = nested.into_iter().peekable();
+            while let Some((i, rest)) = nested.next() {
+                let mut rests = Vec::from([rest]);
+                while let Some((_, r)) = nested.next_if(|(j, _)| *j == i) {
+                    rests.push(r);
+                }
+                a_[i] = del(take(&mut a_[i]), rests)?;
+            }
+
+            // remove every index only once, starting from the end,
+            // such that removing an element does not shift the remaining indices
+            dels.sort_unstable();
+            dels.dedup();
+            for i in dels.into_iter().rev() {
+                a_.remove(i);
+            }
+            Ok(Val::Arr(a))
+        }
+        v => Err(Error::Index(v, paths[0][0].clone())),
+    }
+}
+
+/// Resolve the leading indices and slices of a path into an array to
+/// a range of indices of the array and the remaining path.
+///
+/// If the remaining path is not empty, then the range contains exactly one index.
+/// An index beyond the array yields an empty range and an empty remaining path.
+fn resolve<'a>(a: &[Val], mut path: &'a [Val]) -> Result<(Range<usize>, &'a [Val]), Error> {
+    let mut range = 0..a.len();
+    while let Some((k, rest)) = path.split_first() {
+        let len = range.len();
+        match k {
+            Val::Int(i) => {
+                let abs = if *i < 0 { len as isize + i } else { *i };
+                let abs = usize::try_from(abs).map_err(|_| Error::IndexOutOfBounds(*i))?;
+                let i = range.start + abs;
+                return Ok(if abs < len {
+                    (i..i + 1, rest)
+                } else {
+                    (i..i, &[])
+                });
+            }
+            Val::Obj(_) => {
+                let (skip, n) = slice(k, len)?;
+                range = range.start + skip..range.start + skip + n;
+                path = rest;
+            }
+            k => return Err(Error::Index(Val::arr(a[range].to_vec()), k.clone())),
+        }
+    }
+    Ok((range, path))
+}
+
+/// Replace a value by `null` and return it.
+fn take(v: &mut Val) -> Val {
+    core::mem::replace(v, Val::Null)
+}
+
+/// Return the bound of a slice `{"start": s, "end": e}` with the given name.
+fn bound(k: &Val, name: &str) -> Result<Option<Val>, Error> {
+    match k.clone().index(&Val::str(name.into()))? {
+        Val::Null => Ok(None),
+        i => Ok(Some(Val::Int(i.as_int()?))),
+    }
+}
+
+/// Return the number of elements to skip and to take for a slice of an array of length `len`.
+fn slice(k: &Val, len: usize) -> Result<(usize, usize), Error> {
+    let abs = |i: Option<Val>, default| match i {
+        Some(Val::Int(i)) if i < 0 => len.saturating_sub(i.unsigned_abs()),
+        Some(Val::Int(i)) => core::cmp::min(i as usize, len),
+        _ => default,
+    };
+    let from = abs(bound(k, "start")?, 0);
+    let upto = abs(bound(k, "end")?, len);
+    Ok((from, upto.saturating_sub(from)))
+}
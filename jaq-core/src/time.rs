@@ -1,15 +1,31 @@
+use alloc::format;
 use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use jaq_interpret::{Error, Val, ValR};
 
 /// Parse an ISO-8601 timestamp string to a number holding the equivalent UNIX timestamp
 /// (seconds elapsed since 1970/01/01).
+///
+/// Like in jq, only timestamps of the shape `%Y-%m-%dT%H:%M:%SZ` are accepted.
+/// In particular, any UTC offset other than `Z` is rejected.
+/// Unlike jq, jaq also accepts fractional seconds, yielding a float.
 pub fn from_iso8601(s: &str) -> ValR {
-    use time::format_description::well_known::Iso8601;
-    use time::OffsetDateTime;
-    let datetime = OffsetDateTime::parse(s, &Iso8601::DEFAULT)
-        .map_err(|e| Error::str(format_args!("cannot parse {s} as ISO-8601 timestamp: {e}")))?;
+    use time::{format_description::FormatItem, macros::format_description, PrimitiveDateTime};
+    const FORMAT: &[FormatItem] = format_description!(
+        version = 2,
+        "[year]-[month]-[day]T[hour]:[minute]:[second]Z"
+    );
+    const FORMAT_FRAC: &[FormatItem] = format_description!(
+        version = 2,
+        "[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond]Z"
+    );
+    let frac = s.contains('.');
+    let format = if frac { FORMAT_FRAC } else { FORMAT };
+    let datetime = PrimitiveDateTime::parse(s, format)
+        .map_err(|e| Error::str(format_args!("cannot parse {s} as ISO-8601 timestamp: {e}")))?
+        .assume_utc();
     let epoch_s = datetime.unix_timestamp();
-    if s.contains('.') {
+    if frac {
         let seconds = epoch_s as f64 + (f64::from(datetime.nanosecond()) * 1e-9_f64);
         Ok(Val::Float(seconds))
     } else {
@@ -19,35 +35,268 @@ pub fn from_iso8601(s: &str) -> ValR {
     }
 }
 
-/// Format a number as an ISO-8601 timestamp string.
+/// Format a UNIX timestamp or a "broken down time" array as an ISO-8601 timestamp string.
+///
+/// Like in jq, fractional seconds are truncated.
 pub fn to_iso8601(v: &Val) -> Result<String, Error> {
-    use time::format_description::well_known::iso8601;
+    strftime(v, "%Y-%m-%dT%H:%M:%SZ")
+}
+
+/// Convert a UNIX timestamp to a date-time in UTC.
+fn epoch_to_datetime(v: &Val) -> Result<time::OffsetDateTime, Error> {
     use time::OffsetDateTime;
-    const SECONDS_CONFIG: iso8601::EncodedConfig = iso8601::Config::DEFAULT
-        .set_time_precision(iso8601::TimePrecision::Second {
-            decimal_digits: None,
-        })
-        .encode();
+    let fail = |e| Error::str(format_args!("cannot convert {v} to date-time: {e}"));
+    match v {
+        Val::Int(i) => OffsetDateTime::from_unix_timestamp(*i as i64).map_err(fail),
+        v => {
+            let f_ns = (v.as_float()? * 1_000_000_000_f64).round() as i128;
+            OffsetDateTime::from_unix_timestamp_nanos(f_ns).map_err(fail)
+        }
+    }
+}
 
-    let fail1 = |e| Error::str(format_args!("cannot format {v} as ISO-8601 timestamp: {e}"));
-    let fail2 = |e| Error::str(format_args!("cannot format {v} as ISO-8601 timestamp: {e}"));
+/// Convert a date-time to a "broken down time" array.
+///
+/// The array consists of the year, the month (starting from 0),
+/// the day of the month, the hours, the minutes, the seconds,
+/// the day of the week (starting from 0 on Sunday), and
+/// the day of the year (starting from 0).
+fn datetime_to_array(dt: time::OffsetDateTime) -> Val {
+    let seconds = if dt.nanosecond() > 0 {
+        Val::Float(f64::from(dt.second()) + f64::from(dt.nanosecond()) * 1e-9_f64)
+    } else {
+        Val::Int(dt.second().into())
+    };
+    Val::arr(Vec::from([
+        Val::Int(dt.year() as isize),
+        Val::Int(isize::from(u8::from(dt.month())) - 1),
+        Val::Int(dt.day().into()),
+        Val::Int(dt.hour().into()),
+        Val::Int(dt.minute().into()),
+        seconds,
+        Val::Int(dt.weekday().number_days_from_sunday().into()),
+        Val::Int(dt.ordinal() as isize - 1),
+    ]))
+}
 
-    match v {
-        Val::Num(n) => to_iso8601(&Val::from_dec_str(n)),
-        Val::Float(f) => {
-            let f_ns = (f * 1_000_000_000_f64).round() as i128;
-            OffsetDateTime::from_unix_timestamp_nanos(f_ns)
-                .map_err(fail1)?
-                .format(&iso8601::Iso8601::DEFAULT)
-                .map_err(fail2)
+/// Convert a "broken down time" array to a date-time in UTC.
+///
+/// Only the first six elements of the array are considered;
+/// the day of the week and the day of the year are ignored.
+///
+/// Like `timegm` in jq, fields outside of their usual ranges are normalised;
+/// for example, the 31st of February 2015 is the 3rd of March 2015.
+/// To this end, the month is carried into the year, and
+/// the day, the hours, the minutes, and the seconds are
+/// added as offsets to the first day of the resulting month.
+fn array_to_datetime(v: &Val) -> Result<time::OffsetDateTime, Error> {
+    use time::{Date, Duration, Month};
+    let fail = || Error::str(format_args!("cannot use {v} as broken down time"));
+    let xs = v.as_arr()?.get(..6).ok_or_else(fail)?;
+    let xs = xs
+        .iter()
+        .map(Val::as_float)
+        .collect::<Result<Vec<_>, _>>()?;
+    let (year, month, day, hour, minute, second) = match xs[..] {
+        [y, mo, d, h, mi, s] => (y, mo, d, h, mi, s),
+        _ => unreachable!(),
+    };
+    // truncate a float to an integer, failing instead of saturating
+    let int = |f: f64| {
+        let f = f.trunc();
+        (f >= i64::MIN as f64 && f < i64::MAX as f64).then_some(f as i64)
+    };
+    let datetime = || {
+        let (year, month) = (int(year)?, int(month)?);
+        let year = i32::try_from(year.checked_add(month.div_euclid(12))?).ok()?;
+        let month = Month::try_from(u8::try_from(month.rem_euclid(12) + 1).ok()?).ok()?;
+        let first = Date::from_calendar_date(year, month, 1).ok()?;
+
+        let secs = int(day)?.checked_sub(1)?.checked_mul(24)?;
+        let secs = secs.checked_add(int(hour)?)?.checked_mul(60)?;
+        let secs = secs.checked_add(int(minute)?)?.checked_mul(60)?;
+        let secs = secs.checked_add(int(second)?)?;
+        let ns = (second.fract() * 1e9_f64) as i32;
+        let offset = Duration::new(secs, ns);
+        first.midnight().assume_utc().checked_add(offset)
+    };
+    datetime().ok_or_else(fail)
+}
+
+/// Convert a UNIX timestamp to a "broken down time" array in UTC.
+pub fn gmtime(v: &Val) -> ValR {
+    epoch_to_datetime(v).map(datetime_to_array)
+}
+
+/// Return the offset (in seconds east of UTC) of the local time zone at the given UNIX timestamp.
+///
+/// Like the C library, we determine the local time zone from the `TZ` environment variable,
+/// which may be the name of a file in the time zone database, a path to such a file, or
+/// a POSIX time zone rule.
+/// If `TZ` is not set, we use `/etc/localtime`, and if all this fails, UTC.
+#[cfg(feature = "std")]
+fn local_offset(t: i64) -> i32 {
+    use tz::TimeZone;
+    let zone = match std::env::var("TZ") {
+        Ok(tz) => TimeZone::from_posix_tz(&tz),
+        Err(_) => TimeZone::local(),
+    };
+    let offset = zone
+        .ok()
+        .and_then(|zone| Some(zone.find_local_time_type(t).ok()?.ut_offset()));
+    offset.unwrap_or(0)
+}
+
+/// Convert a UNIX timestamp to a "broken down time" array in the local time zone.
+///
+/// The local time zone is determined by the `TZ` environment variable.
+/// Without the `std` feature, the local time zone is UTC.
+pub fn localtime(v: &Val) -> ValR {
+    let dt = epoch_to_datetime(v)?;
+    #[cfg(feature = "std")]
+    let offset = local_offset(dt.unix_timestamp());
+    #[cfg(not(feature = "std"))]
+    let offset = 0;
+    let offset = time::UtcOffset::from_whole_seconds(offset).unwrap_or(time::UtcOffset::UTC);
+    Ok(datetime_to_array(dt.to_offset(offset)))
+}
+
+/// Convert a "broken down time" array in UTC to a UNIX timestamp.
+///
+/// Fractional seconds are truncated, yielding an integer like in jq.
+pub fn mktime(v: &Val) -> ValR {
+    let epoch_s = array_to_datetime(v)?.unix_timestamp();
+    isize::try_from(epoch_s)
+        .map(Val::Int)
+        .or_else(|_| Ok(Val::Num(epoch_s.to_string().into())))
+}
+
+/// Return the `time` format description corresponding to a `strftime` conversion specifier.
+fn specifier(c: char) -> Option<&'static str> {
+    Some(match c {
+        'a' => "[weekday repr:short]",
+        'A' => "[weekday]",
+        'b' | 'h' => "[month repr:short]",
+        'B' => "[month repr:long]",
+        'c' => "[weekday repr:short] [month repr:short] [day padding:space] [hour]:[minute]:[second] [year]",
+        'd' => "[day]",
+        'D' => "[month]/[day]/[year repr:last_two]",
+        'e' => "[day padding:space]",
+        'F' => "[year]-[month]-[day]",
+        'g' => "[year base:iso_week repr:last_two]",
+        'G' => "[year base:iso_week]",
+        'H' => "[hour]",
+        'I' => "[hour repr:12]",
+        'j' => "[ordinal]",
+        'k' => "[hour padding:space]",
+        'l' => "[hour repr:12 padding:space]",
+        'm' => "[month]",
+        'M' => "[minute]",
+        'n' => "\n",
+        'p' => "[period]",
+        'P' => "[period case:lower]",
+        'r' => "[hour repr:12]:[minute]:[second] [period]",
+        'R' => "[hour]:[minute]",
+        's' => "[unix_timestamp]",
+        'S' => "[second]",
+        't' => "\t",
+        'T' => "[hour]:[minute]:[second]",
+        'u' => "[weekday repr:monday]",
+        'U' => "[week_number repr:sunday]",
+        'V' => "[week_number]",
+        'w' => "[weekday repr:sunday one_indexed:false]",
+        'W' => "[week_number repr:monday]",
+        'x' => "[month]/[day]/[year repr:last_two]",
+        'X' => "[hour]:[minute]:[second]",
+        'y' => "[year repr:last_two]",
+        'Y' => "[year]",
+        'z' => "[offset_hour sign:mandatory][offset_minute]",
+        'Z' => "UTC",
+        '%' => "%",
+        _ => return None,
+    })
+}
+
+/// Convert a `strftime` format string to a `time` format description.
+///
+/// `time` cannot express the century (`%C`), so when formatting a date-time `dt`,
+/// we insert the century of `dt` literally, and when parsing, we reject it.
+fn description(fmt: &str, dt: Option<&time::OffsetDateTime>) -> Result<String, Error> {
+    let mut desc = String::new();
+    let mut chars = fmt.chars();
+    let fail = || Error::str(format_args!("invalid format {fmt}"));
+    while let Some(c) = chars.next() {
+        match c {
+            '%' => match (chars.next(), dt) {
+                (Some('C'), Some(dt)) => desc += &format!("{:02}", dt.year().div_euclid(100)),
+                (spec, _) => desc += spec.and_then(specifier).ok_or_else(fail)?,
+            },
+            '[' | ']' | '\\' => {
+                desc.push('\\');
+                desc.push(c)
+            }
+            c => desc.push(c),
+        }
+    }
+    Ok(desc)
+}
+
+/// Format a UNIX timestamp or a "broken down time" array with a `strftime` format string.
+pub fn strftime(v: &Val, fmt: &str) -> Result<String, Error> {
+    let dt = match v {
+        Val::Arr(_) => array_to_datetime(v)?,
+        v => epoch_to_datetime(v)?,
+    };
+    let desc = description(fmt, Some(&dt))?;
+    let items = time::format_description::parse_borrowed::<2>(&desc).map_err(Error::str)?;
+    let fail = |e| Error::str(format_args!("cannot format {v} with {fmt}: {e}"));
+    dt.format(&items).map_err(fail)
+}
+
+/// Parse a string with a `strftime` format string to a "broken down time" array.
+///
+/// Like in jq, missing components default to those of 1900-01-01T00:00:00,
+/// and a parsed UTC offset (`%z`) is ignored,
+/// so the result holds the date and time as written.
+/// For `%s`, the result is in UTC.
+pub fn strptime(s: &str, fmt: &str) -> ValR {
+    use core::num::NonZeroU8;
+    use time::{parsing::Parsed, Date, Month, OffsetDateTime, Time};
+    let desc = description(fmt, None)?;
+    let items = time::format_description::parse_borrowed::<2>(&desc).map_err(Error::str)?;
+    let fail = || Error::str(format_args!("date \"{s}\" does not match format \"{fmt}\""));
+
+    let mut parsed = Parsed::new();
+    let rest = parsed
+        .parse_items(s.as_bytes(), &items)
+        .map_err(|_| fail())?;
+    if !rest.is_empty() {
+        return Err(fail());
+    }
+    if let Some(ns) = parsed.unix_timestamp_nanos() {
+        let dt = OffsetDateTime::from_unix_timestamp_nanos(ns).map_err(|_| fail())?;
+        return Ok(datetime_to_array(dt));
+    }
+    let date = Date::try_from(parsed).ok().or_else(|| {
+        if parsed.year().is_none() {
+            // like in glibc, `%y` yields years from 1969 to 2068
+            let year = parsed
+                .year_last_two()
+                .map_or(1900, |y| i32::from(y) + if y < 69 { 2000 } else { 1900 });
+            parsed.set_year(year)?;
+        }
+        if parsed.month().is_none() {
+            parsed.set_month(Month::January)?;
         }
-        Val::Int(i) => {
-            let iso8601_fmt_s = iso8601::Iso8601::<SECONDS_CONFIG>;
-            OffsetDateTime::from_unix_timestamp(*i as i64)
-                .map_err(fail1)?
-                .format(&iso8601_fmt_s)
-                .map_err(fail2)
+        if parsed.day().is_none() {
+            parsed.set_day(NonZeroU8::new(1)?)?;
         }
-        _ => todo!(),
+        Date::try_from(parsed).ok()
+    });
+    let date = date.ok_or_else(fail)?;
+    if parsed.minute().is_none() {
+        parsed.set_minute(0);
     }
+    let time = Time::try_from(parsed).unwrap_or(Time::MIDNIGHT);
+    Ok(datetime_to_array(date.with_time(time).assume_utc()))
 }
@@ -9,6 +9,23 @@ fn yields(x: jaq_interpret::Val, f: &str, ys: impl Iterator<Item = jaq_interpret
     ctx.yields(x, f.unwrap(), ys)
 }
 
+/// Run a filter on the given input and return its outputs.
+///
+/// Unlike [`give`], this allows for distinguishing integers from floats.
+pub fn run(x: Value, f: &str) -> Vec<jaq_interpret::ValR> {
+    use jaq_interpret::{Ctx, FilterT, RcIter};
+    let mut ctx = jaq_interpret::ParseCtx::new(Vec::new());
+    ctx.insert_natives(jaq_core::core());
+
+    let (f, errs) = jaq_parse::parse(f, jaq_parse::main());
+    assert!(errs.is_empty());
+    let f = ctx.compile(f.unwrap());
+    assert!(ctx.errs.is_empty());
+
+    let inputs = RcIter::new(core::iter::empty());
+    f.run((Ctx::new([], &inputs), x.into())).collect()
+}
+
 pub fn fail(x: Value, f: &str, err: jaq_interpret::Error) {
     yields(x.into(), f, core::iter::once(Err(err)))
 }
@@ -0,0 +1,100 @@
+//! Tests for `localtime`.
+//!
+//! These tests set the `TZ` environment variable of the process,
+//! so they are kept apart from all other tests, which run in parallel.
+
+pub mod common;
+
+use common::give;
+use serde_json::json;
+
+#[test]
+fn localtime() {
+    let tz = |tz| std::env::set_var("TZ", tz);
+    let f = "[1700000000, 1690000000, 1711846799, 1711846800 | localtime | .[3:5]]";
+
+    tz("UTC0");
+    give(
+        json!(1700000000),
+        "localtime",
+        json!([2023, 10, 14, 22, 13, 20, 2, 317]),
+    );
+    tz("JST-9");
+    give(
+        json!(1700000000),
+        "localtime",
+        json!([2023, 10, 15, 7, 13, 20, 3, 318]),
+    );
+
+    // daylight saving time from the last Sunday in March to the last Sunday in October
+    tz("CET-1CEST,M3.5.0,M10.5.0/3");
+    give(json!(null), f, json!([[23, 13], [6, 26], [1, 59], [3, 0]]));
+    // daylight saving time from October to April
+    tz("AEST-10AEDT,M10.1.0,M4.1.0/3");
+    give(
+        json!(null),
+        f,
+        json!([[9, 13], [14, 26], [11, 59], [12, 0]]),
+    );
+
+    // malformed rules, such as ones with overflowing numbers, yield UTC
+    let malformed = [
+        "UTC999999",
+        "UTC-999999",
+        "UTC0:99999999",
+        "UTC2147483648",
+        "UTC+",
+        "<UTC",
+        "CET-1CEST2147483647",
+        "CET-1CEST,M3.5.0",
+        "CET-1CEST,M3.5.0,M10.5.0,",
+        "CET-1CEST,J2147483647,M10.5.0",
+        "CET-1CEST,2147483647,M10.5.0",
+        "CET-1CEST,M2147483647.5.0,M10.5.0",
+        "CET-1CEST,M3.2147483647.0,M10.5.0",
+        "CET-1CEST,M3.5.2147483647,M10.5.0",
+        "CET-1CEST,M3.5.0/2147483647,M10.5.0",
+    ];
+    for rule in malformed {
+        tz(rule);
+        give(json!(1700000000), "localtime == gmtime", json!(true));
+    }
+
+    // TZif file that switches to daylight saving time at 1690000000 and back at 1700000000,
+    // followed by the rule for central Europe
+    let header = |counts: [u32; 6]| {
+        let mut header = b"TZif2".to_vec();
+        header.extend([0; 15]);
+        counts.iter().for_each(|c| header.extend(c.to_be_bytes()));
+        header
+    };
+    // the version 1 data block is minimal, consisting of a single UTC time type
+    let mut tzif = header([0, 0, 0, 0, 1, 1]);
+    tzif.extend([0; 7]);
+    tzif.extend(header([0, 0, 0, 2, 2, 9]));
+    tzif.extend(1690000000_i64.to_be_bytes());
+    tzif.extend(1700000000_i64.to_be_bytes());
+    tzif.extend([1, 0]);
+    tzif.extend(3600_i32.to_be_bytes());
+    tzif.extend([0, 0]);
+    tzif.extend(7200_i32.to_be_bytes());
+    tzif.extend([1, 4]);
+    tzif.extend(b"CET\0CEST\0\nCET-1CEST,M3.5.0,M10.5.0/3\n");
+
+    let path = std::env::temp_dir().join(format!("jaq-tzif-{}", std::process::id()));
+    let write = |data: &[u8]| std::fs::write(&path, data).unwrap();
+    tz(path.to_str().unwrap());
+    write(&tzif);
+    give(json!(null), f, json!([[23, 13], [6, 26], [1, 59], [3, 0]]));
+
+    // truncated and corrupted TZif files must not make jaq panic
+    for i in 0..tzif.len() {
+        write(&tzif[..i]);
+        give(json!(1700000000), "localtime | length", json!(8));
+        let mut corrupt = tzif.clone();
+        corrupt[i] = 0xff;
+        write(&corrupt);
+        give(json!(1700000000), "localtime | length", json!(8));
+    }
+    std::fs::remove_file(&path).unwrap();
+}
@@ -2,7 +2,7 @@
 
 pub mod common;
 
-use common::{fail, give, gives};
+use common::{fail, give, gives, run};
 use jaq_interpret::error::{Error, Type};
 use jaq_interpret::Val;
 use serde_json::json;
@@ -31,10 +31,65 @@ yields!(
     [2, 3, 3, 4]
 );
 
+// errors on the left-hand side of `//` are treated like no output
+yields!(alt_error, "[error // 2]", [2]);
+yields!(alt_error_some, "[(error, 1) // 2]", [1]);
+yields!(alt_error_falsy, "[(0 == 1, error) // 2]", [2]);
+
 #[test]
 fn ascii() {
     give(json!("aAaAäの"), "ascii_upcase", json!("AAAAäの"));
     give(json!("aAaAäの"), "ascii_downcase", json!("aaaaäの"));
+    // combining characters are preserved, even after ASCII letters
+    give(
+        json!("A\u{301}ÉE\u{301}"),
+        "ascii_downcase",
+        json!("a\u{301}Ée\u{301}"),
+    );
+    give(
+        json!("a\u{301}ée\u{301}"),
+        "ascii_upcase",
+        json!("A\u{301}éE\u{301}"),
+    );
+}
+
+#[test]
+fn bsearch() {
+    let f = "[bsearch(0, 1, 2, 3, 4, 5, 6)]";
+    give(json!([1, 3, 5]), f, json!([-1, 0, -2, 1, -3, 2, -4]));
+    give(json!([]), "bsearch(1)", json!(-1));
+    // the index of the first occurrence is returned
+    give(json!([1, 3, 3, 3, 5]), "bsearch(3)", json!(1));
+    // values are compared with the usual ordering
+    give(
+        json!([null, false, 1, "a", [], {}]),
+        r#"[bsearch("a", 0.5)]"#,
+        json!([3, -3]),
+    );
+    let err = Error::Type(Val::from(json!({"a": 1})), Type::Arr);
+    fail(json!({"a": 1}), "bsearch(1)", err);
+}
+
+#[test]
+fn contains() {
+    give(json!("foobar"), r#"contains("bar")"#, json!(true));
+    give(json!("foobar"), r#"contains("baz")"#, json!(false));
+    // arrays are compared like multisets, ignoring multiplicity
+    give(json!([1, [2, 3]]), "contains([[2], 1, 1])", json!(true));
+    give(json!([1, [2, 3]]), "contains([[4]])", json!(false));
+
+    // every key of the argument must be contained in the value of the input
+    let abc = json!({"a": [1, {"b": "xyz"}], "c": 1});
+    give(abc.clone(), r#"contains({a: [{b: "y"}]})"#, json!(true));
+    give(abc.clone(), r#"contains({a: [{b: "w"}]})"#, json!(false));
+    give(abc.clone(), r#"contains({d: {}.a})"#, json!(false));
+    // values of different types are not contained in each other
+    give(abc.clone(), r#"contains({c: "1"})"#, json!(false));
+
+    give(json!(1), "contains(1.0)", json!(true));
+    give(json!(null), "contains({}.a)", json!(true));
+    let err = Error::str("cannot check whether 1 contains \"1\"");
+    fail(json!(1), r#"contains("1")"#, err);
 }
 
 #[test]
@@ -44,17 +99,51 @@ fn dateiso8601() {
         "fromdateiso8601",
         json!(86400),
     );
+    // unlike jq, jaq accepts fractional seconds
     give(
         json!("1970-01-02T00:00:00.123456789Z"),
         "fromdateiso8601",
         json!(86400.123456789),
     );
     give(json!(86400), "todateiso8601", json!("1970-01-02T00:00:00Z"));
+    // like in jq, fractional seconds are truncated
     give(
-        json!(86400.123456789),
+        json!(86400.923456789),
         "todateiso8601",
-        json!("1970-01-02T00:00:00.123456789Z"),
+        json!("1970-01-02T00:00:00Z"),
     );
+    give(
+        json!([1970, 0, 2, 0, 0, 0, 5, 1]),
+        "todateiso8601",
+        json!("1970-01-02T00:00:00Z"),
+    );
+    let err = Error::Type(Val::from("1".to_string()), Type::Float);
+    fail(json!("1"), "todateiso8601", err);
+}
+
+#[test]
+fn delpaths() {
+    let x = json!([[1, 2], 2, 3, 2]);
+    give(x.clone(), "delpaths([[1], [3]])", json!([[1, 2], 3]));
+    give(x.clone(), "delpaths([[3], [1]])", json!([[1, 2], 3]));
+    give(
+        x.clone(),
+        r#"delpaths([[0, 0], [{"start": 2}]])"#,
+        json!([[2], 2]),
+    );
+    give(x.clone(), "delpaths([[5], [-1]])", json!([[1, 2], 2, 3]));
+    give(x.clone(), "delpaths([[]])", json!(null));
+    give(x.clone(), "delpaths([[0], [0], [-4]])", json!([2, 3, 2]));
+    give(
+        x.clone(),
+        r#"delpaths([[{"start": 1}, 0], [{"end": -2}], [1]])"#,
+        json!([3, 2]),
+    );
+    fail(x, "delpaths([[-5]])", Error::IndexOutOfBounds(-5));
+
+    let x = json!({"a": {"x": 1, "y": 2}, "b": 1, "c": 3});
+    let y = json!({"a": {"y": 2}, "c": 3});
+    give(x, r#"delpaths([["a", "x"], ["b"], ["d", "e"]])"#, y);
 }
 
 #[test]
@@ -76,6 +165,13 @@ yields!(
     r#"[0, 0 == 0, {}.a, "hello", {}, [] | @text]"#,
     ["0", "true", "null", "hello", "{}", "[]"]
 );
+// `@text` formats interpolated values like `tostring`
+yields!(format_text_interpolation, r#"@text "x=\(1+1)""#, "x=2");
+yields!(
+    format_text_values,
+    r#"[0, "a", [1, "b"], {"c": {}.a} | @text "\(.)"]"#,
+    ["0", "a", "[1,\"b\"]", "{\"c\":null}"]
+);
 yields!(
     format_json,
     r#"[0, 0 == 0, {}.a, "hello", {}, [] | @json]"#,
@@ -96,6 +192,17 @@ yields!(
     r#"[0, 0 == 0, {}.a, "hello \"quotes\" and, commas"] | @csv"#,
     r#"0,true,,"hello ""quotes"" and, commas""#
 );
+// strings are always quoted, so they may contain line breaks (RFC 4180)
+yields!(
+    format_csv_newlines,
+    r#"["line1\nline2", "a,\"b\"\r\nc", "", 1] | @csv"#,
+    "\"line1\nline2\",\"a,\"\"b\"\"\r\nc\",\"\",1"
+);
+yields!(
+    format_csv_invalid,
+    r#"[[[1]], [{}] | try @csv catch "invalid"]"#,
+    ["invalid", "invalid"]
+);
 yields!(
     format_tsv,
     r#"[0, 0 == 0, {}.a, "hello \"quotes\" and \n\r\t\\ escapes"] | @tsv"#,
@@ -111,6 +218,52 @@ yields!(
     r#""hello cruel world" | @base64 | @base64d"#,
     "hello cruel world"
 );
+yields!(format_base64_empty, r#""" | [@base64, @base64d]"#, ["", ""]);
+yields!(
+    format_unformat_base64_malformed,
+    r#""a" | try @base64d catch -1"#,
+    -1
+);
+yields!(
+    format_base32,
+    r#"["", "f", "fo", "foo", "foob", "fooba", "foobar"] | [.[] | @base32]"#,
+    [
+        "",
+        "MY======",
+        "MZXQ====",
+        "MZXW6===",
+        "MZXW6YQ=",
+        "MZXW6YTB",
+        "MZXW6YTBOI======"
+    ]
+);
+yields!(
+    format_unformat_base32,
+    r#""hello cruel wörld" | @base32 | @base32d"#,
+    "hello cruel wörld"
+);
+// invalid length, padding, alphabet, and trailing bits
+yields!(
+    format_unformat_base32_malformed,
+    r#"["MZXW6=", "MZ======", "MZXW6===MZXW6===", "mzxw6===", "MZXW7==="]
+    | [.[] | try @base32d catch -1]"#,
+    [-1, -1, -1, -1, -1]
+);
+yields!(
+    format_base16,
+    r#""hello wörld" | @base16"#,
+    "68656c6c6f2077c3b6726c64"
+);
+yields!(
+    format_unformat_base16,
+    r#""68656C6c6f" | @base16d"#,
+    "hello"
+);
+yields!(
+    format_unformat_base16_malformed,
+    r#"[("6", "6g", "ff") | try @base16d catch -1]"#,
+    [-1, -1, -1]
+);
 yields!(
     format_sh,
     r#"[0, 0 == 0, {}.a, "O'Hara!", ["Here", "there"] | @sh]"#,
@@ -127,6 +280,72 @@ yields!(
     -1
 );
 
+#[test]
+fn gmtime() {
+    let epoch = json!([1970, 0, 1, 0, 0, 0, 4, 0]);
+    give(json!(0), "gmtime", epoch);
+    let nov = json!([2023, 10, 14, 22, 13, 20, 2, 317]);
+    give(json!(1700000000), "gmtime", nov);
+    let leap = json!([2024, 1, 29, 12, 0, 30.5, 4, 59]);
+    give(json!(1709208030.5), "gmtime", leap);
+}
+
+yields!(gmtime_mktime, "1700000000 | gmtime | mktime", 1700000000);
+yields!(
+    mktime_frac,
+    "[2023, 10, 14, 22, 13, 20.9] | mktime",
+    1700000000
+);
+yields!(
+    mktime_invalid,
+    "[[2023, 0], [1e300, 0, 1, 0, 0, 0], [2023, 0, 1, 0, 0, 1e300] | try mktime catch -1]",
+    [-1, -1, -1]
+);
+yields!(
+    gmtime_mktime_many,
+    "[-2000000000, 0, 4102444800 | gmtime | mktime]",
+    json!([-2000000000, 0, 4102444800_i64])
+);
+
+#[test]
+fn mktime_overflow() {
+    // like in jq, fields outside their ranges are normalised
+    let f = "mktime | todateiso8601";
+    let tests = [
+        (json!([2015, 1, 31, 0, 0, 0]), "2015-03-03T00:00:00Z"),
+        (json!([2015, 14, 5, 0, 0, 0]), "2016-03-05T00:00:00Z"),
+        (json!([2015, -1, 5, 0, 0, 0]), "2014-12-05T00:00:00Z"),
+        (json!([2015, 2, 0, 0, 0, 0]), "2015-02-28T00:00:00Z"),
+        (json!([2015, 2, 1, 25, 61, -1]), "2015-03-02T02:00:59Z"),
+    ];
+    for (bdt, iso) in tests {
+        give(bdt, f, json!(iso));
+    }
+    let bdt = json!([2015, 1, 31, 0, 0, 0, 0, 0]);
+    give(bdt, "mktime", json!(1425340800));
+    let f = "gmtime | .[1] += 1 | mktime | todateiso8601";
+    give(json!(1422662400), f, json!("2015-03-03T00:00:00Z"));
+}
+
+#[test]
+fn getpath() {
+    let x = json!({"a": null, "b": [1, 2, 3]});
+    give(x.clone(), r#"getpath(["a", "c", 0])"#, json!(null));
+    give(x.clone(), r#"getpath(["b", -1])"#, json!(3));
+    give(x.clone(), r#"getpath(["b", {"start": 1}])"#, json!([2, 3]));
+    give(
+        x.clone(),
+        r#"[path(getpath(["a", "c"]))]"#,
+        json!([["a", "c"]]),
+    );
+
+    fail(
+        x,
+        r#"getpath(["b", 0, "c"])"#,
+        Error::Type(Val::Int(1), Type::Iter),
+    );
+}
+
 #[test]
 fn group_by() {
     gives(json!([]), "group_by(.)", [json!([])]);
@@ -151,6 +370,8 @@ fn has() {
     give(json!([0, null]), "has(0)", json!(true));
     give(json!([0, null]), "has(1)", json!(true));
     give(json!([0, null]), "has(2)", json!(false));
+    // like jq, and unlike `.[-1]`
+    give(json!([0, null]), "has(-1)", json!(false));
 
     give(json!({"a": 1, "b": null}), r#"has("a")"#, json!(true));
     give(json!({"a": 1, "b": null}), r#"has("b")"#, json!(true));
@@ -158,6 +379,7 @@ fn has() {
 }
 
 yields!(indices_str, r#""a,b, cd, efg" | indices(", ")"#, [3, 7]);
+yields!(indices_null, "{}.a | indices(1)", json!(null));
 yields!(
     indices_arr_num,
     "[0, 1, 2, 1, 3, 1, 4] | indices(1)",
@@ -180,12 +402,22 @@ yields!(indices_str_gb2, r#""🇬🇧🇬🇧" | indices("🇬🇧")"#, [0, 2]);
 
 #[test]
 fn json() {
-    // TODO: correct this
-    give(json!(1.0), "tojson", json!("1.0"));
-    give(json!(0), "1.0 | tojson", json!("1.0"));
+    // floats are written like jq writes them
+    give(json!(10), ". / 2 | tojson", json!("5"));
+    give(json!(0), "1.0 * 1 | tojson", json!("1"));
+    give(
+        json!(0),
+        "[10 / 2, 1e100 + 0, -0.0] | tojson",
+        json!("[5,1e+100,-0]"),
+    );
+    give(json!(0), "1e100 + 0 | tostring", json!("1e+100"));
     give(json!(0), "1.1 | tojson", json!("1.1"));
     give(json!(0), "0.0 / 0.0 | tojson", json!("null"));
-    give(json!(0), "1.0 / 0.0 | tojson", json!("null"));
+    give(
+        json!(0),
+        "1.0 / 0.0 | [., -.] | tojson",
+        json!("[1.7976931348623157e+308,-1.7976931348623157e+308]"),
+    );
 }
 
 #[test]
@@ -206,6 +438,21 @@ yields!(length_int_neg, "-2 | length", 2);
 yields!(length_float_pos, " 2.5 | length", 2.5);
 yields!(length_float_neg, "-2.5 | length", 2.5);
 
+// strings are not quoted
+yields!(
+    tostring,
+    r#"[0, 1.5, 0 == 0, {}.a, "hello", {"a": "b"}, ["c"] | tostring]"#,
+    [
+        "0",
+        "1.5",
+        "true",
+        "null",
+        "hello",
+        r#"{"a":"b"}"#,
+        r#"["c"]"#
+    ]
+);
+
 yields!(utf8bytelength_foo1, r#""foo" | utf8bytelength"#, 3);
 yields!(utf8bytelength_foo2, r#""ƒoo" | utf8bytelength"#, 4);
 yields!(utf8bytelength_namaste, r#""नमस्ते" | utf8bytelength"#, 18);
@@ -218,6 +465,18 @@ fn limit() {
 
     // here, jaq diverges from jq, which returns `[0, 1]`
     give(json!(null), "[limit(-1; 0, 1)]", json!([]));
+
+    // the generator is not evaluated at all if no outputs are requested
+    give(json!(null), "[limit(0; error)]", json!([]));
+    give(json!(null), "def f: f; [limit(0; f)]", json!([]));
+    // the generator is not evaluated beyond the requested outputs
+    give(json!(null), "[limit(2; 1, 2, error)]", json!([1, 2]));
+
+    // `reduce` consumes only the outputs of `limit`
+    let f = "reduce limit(3; .[]) as $x (0; . + $x)";
+    give(json!((1..=10000).collect::<Vec<_>>()), f, json!(6));
+    let f = "reduce limit(3; range(0; 1/0; 1), error) as $x (0; . + $x)";
+    give(json!(null), f, json!(3));
 }
 
 yields!(min_empty, "[] | min_by(.)", json!(null));
@@ -258,6 +517,124 @@ yields!(
     [10.0, 11.0, 12.0, 13.0, 7.0, 8.0, 8.0, 9.0]
 );
 
+yields!(math_sqrt, "2 | sqrt", core::f64::consts::SQRT_2);
+yields!(
+    math_frexp,
+    "[8, -0.75, 0 | frexp]",
+    json!([[0.5, 4], [-0.75, 0], [0.0, 0]])
+);
+yields!(math_ldexp, "[ldexp(0.5; 4), ldexp(3; -1)]", [8.0, 1.5]);
+yields!(
+    math_2_argument_float_filters,
+    "[atan2(0; -1), fmin(1; 2), fmax(1; 2), copysign(3; -0.0)] | .[0] |= (. == (-1 | acos))",
+    json!([true, 1.0, 2.0, -3.0])
+);
+
+#[test]
+fn math_non_num() {
+    fail(
+        json!("a"),
+        "sqrt",
+        Error::Type(json!("a").into(), Type::Float),
+    );
+    fail(
+        json!(1),
+        "pow(.; {})",
+        Error::Type(json!({}).into(), Type::Float),
+    );
+}
+
+#[test]
+fn path() {
+    let abc = json!({"a": 1, "b": [2, 3]});
+    gives(
+        abc.clone(),
+        "path(.a, .b[])",
+        [json!(["a"]), json!(["b", 0]), json!(["b", 1])],
+    );
+    give(
+        abc.clone(),
+        "[path(.b[-1], .b[1:])]",
+        json!([["b", -1], ["b", {"start": 1}]]),
+    );
+    give(
+        abc.clone(),
+        "[path(first(.b[]), limit(1; .[]))]",
+        json!([["b", 0], ["a"]]),
+    );
+    give(
+        abc.clone(),
+        "[path(.c // .a, if .a then .b else empty end)]",
+        json!([["a"], ["b"]]),
+    );
+    give(abc, "[path(.a as $x | .b | .[$x])]", json!([["b", 1]]));
+
+    give(json!([[1]]), "[path(..)]", json!([[], [0], [0, 0]]));
+    give(
+        json!([[1]]),
+        "path(reduce (0, 0) as $i (.; .[$i]))",
+        json!([0, 0]),
+    );
+    give(
+        json!([[[1]]]),
+        "[path(foreach (0, 0) as $i (.; .[$i]; .[$i]))]",
+        json!([[0, 0], [0, 0, 0]]),
+    );
+    give(json!(0), "[path(empty)]", json!([]));
+    give(json!(null), "path(.a[0].b)", json!(["a", 0, "b"]));
+    // non-path expressions fail
+    fail(json!({"a": 1}), "path(.a | -.)", Error::PathExp);
+    // unlike `.a`, `path(.a)` does not fail on `null`
+    give(json!(null), "path(.a[0])", json!(["a", 0]));
+
+    fail(json!({}), "path(1)", Error::PathExp);
+    fail(json!({}), "path(.a + 1)", Error::PathExp);
+    // errors on the left of `//` are only suppressed outside of paths
+    fail(json!({"a": 1}), "path(.a + 1 // .b)", Error::PathExp);
+    fail(json!({"a": 1}), "path(1 // .a)", Error::PathExp);
+    // folding `1 // .a` to `1` does not change its paths
+    fail(json!({"a": 1}), "1 as $x | path($x // .a)", Error::PathExp);
+}
+
+#[test]
+fn update_paths() {
+    let a = json!({"a": [1, 2, 3]});
+    give(a.clone(), "first(.a[]) |= 0", json!({"a": [0, 2, 3]}));
+    give(a.clone(), "limit(2; .a[]) |= 0", json!({"a": [0, 0, 3]}));
+    give(
+        a.clone(),
+        r#"getpath(["a", -1]) |= 0"#,
+        json!({"a": [1, 2, 0]}),
+    );
+    give(a.clone(), "empty |= 0", a.clone());
+    fail(a.clone(), "error |= 0", Error::Val(Val::from(a)));
+}
+
+#[test]
+fn setpath() {
+    give(
+        json!(null),
+        r#"setpath(["a", 1]; 0)"#,
+        json!({"a": [null, 0]}),
+    );
+    give(
+        json!({"a": 1, "b": 2}),
+        r#"setpath(["a"]; 0)"#,
+        json!({"a": 0, "b": 2}),
+    );
+    give(json!([1, 2, 3]), "setpath([-1]; 0)", json!([1, 2, 0]));
+    give(
+        json!([1, 2, 3]),
+        r#"setpath([{"start": 1}]; [0])"#,
+        json!([1, 0]),
+    );
+    give(json!(1), "setpath([]; 0)", json!(0));
+
+    fail(json!([]), "setpath([-1]; 0)", Error::IndexOutOfBounds(-1));
+    let err = Error::Index(Val::Int(1), Val::from("a".to_string()));
+    fail(json!(1), r#"setpath(["a"]; 0)"#, err);
+}
+
 yields!(range_pp, "[range(0; 6;  2)]", [0, 2, 4]);
 yields!(range_pn, "[range(0; 6; -2)]", json!([]));
 yields!(range_np, "[range(0; -6; 2)]", json!([]));
@@ -299,6 +676,21 @@ fn regex() {
     give(json!(s), &f("split_matches", date, ""), out);
 }
 
+#[test]
+fn reverse() {
+    give(json!([1, [2], 3]), "reverse", json!([3, [2], 1]));
+    give(json!([]), "reverse", json!([]));
+    // strings are reversed by code points, not by bytes
+    give(json!("abc"), "reverse", json!("cba"));
+    give(json!("héllo wörld"), "reverse", json!("dlröw olléh"));
+    give(json!(""), "reverse", json!(""));
+    give(json!(null), "reverse", json!(null));
+
+    let err = |v| Error::Type(Val::from(v), Type::Arr);
+    fail(json!(0), "reverse", err(json!(0)));
+    fail(json!({}), "reverse", err(json!({})));
+}
+
 #[test]
 fn round() {
     give(json!(1), "round", json!(1));
@@ -319,6 +711,123 @@ fn round() {
     fail(json!({}), "round", err(json!({})));
 }
 
+// integers and floats are ordered by their numeric value,
+// and equal numbers keep their original order
+#[test]
+fn sort_num() {
+    // yield each number with whether it is an integer
+    let sort = |xs| match &run(json!(null), &format!("{xs} | sort"))[..] {
+        [Ok(Val::Arr(a))] => a
+            .iter()
+            .map(|v| match v {
+                Val::Int(i) => (*i as f64, true),
+                v => (v.as_float().unwrap(), false),
+            })
+            .collect::<Vec<_>>(),
+        ys => panic!("unexpected output: {ys:?}"),
+    };
+
+    let (i, f) = (true, false);
+    let sorted = sort("[1.0, 1, 0.5]");
+    assert_eq!(sorted, [(0.5, f), (1.0, f), (1.0, i)]);
+
+    let sorted = sort("[2, 1, 1.0, 0.5, 1, 2.0]");
+    let expected = [(0.5, f), (1.0, i), (1.0, f), (1.0, i), (2.0, i), (2.0, f)];
+    assert_eq!(sorted, expected);
+}
+
+// values are only logged, the input is passed through
+yields!(stderr, r#"[1, "a" | stderr]"#, json!([1, "a"]));
+
+#[test]
+fn strftime_strptime() {
+    let fmt = r#""%Y-%m-%dT%H:%M:%SZ""#;
+    let iso = json!("2015-03-05T23:51:47Z");
+    let bdt = json!([2015, 2, 5, 23, 51, 47, 4, 63]);
+    give(bdt.clone(), &format!("strftime({fmt})"), iso.clone());
+    give(json!(1425599507), &format!("strftime({fmt})"), iso.clone());
+    give(iso.clone(), &format!("strptime({fmt})"), bdt.clone());
+    give(
+        iso.clone(),
+        &format!("strptime({fmt}) | strftime({fmt})"),
+        iso,
+    );
+    give(
+        bdt.clone(),
+        &format!("strftime({fmt}) | strptime({fmt})"),
+        bdt,
+    );
+
+    let f = r#"strftime("%A, %B %e, %Y [%j] \\ %I %p %%")"#;
+    give(
+        json!(1425599507),
+        f,
+        json!("Thursday, March  5, 2015 [064] \\ 11 PM %"),
+    );
+}
+
+#[test]
+fn strftime_specifiers() {
+    let f = r#"strftime("%s %C %g %k %l %x %X %P")"#;
+    let out = "1425599507 20 15 23 11 03/05/15 23:51:47 pm";
+    give(json!(1425599507), f, json!(out));
+    give(json!(0), r#"strftime("%k|%l|%P")"#, json!(" 0|12|am"));
+
+    let bdt = json!([2015, 2, 5, 23, 51, 47, 4, 63]);
+    give(json!("1425599507"), r#"strptime("%s")"#, bdt);
+    give(
+        json!(-1),
+        r#"strftime("%s") | strptime("%s") | mktime"#,
+        json!(-1),
+    );
+}
+
+#[test]
+fn strptime_defaults() {
+    // like in jq, missing components default to those of 1900-01-01T00:00:00
+    let f = r#"strptime("%H:%M")"#;
+    give(json!("10:00"), f, json!([1900, 0, 1, 10, 0, 0, 1, 0]));
+    let f = r#"strptime("%Y")"#;
+    give(json!("2015"), f, json!([2015, 0, 1, 0, 0, 0, 4, 0]));
+    let f = r#"strptime("%y-%m-%d %H")"#;
+    give(
+        json!("15-03-05 23"),
+        f,
+        json!([2015, 2, 5, 23, 0, 0, 4, 63]),
+    );
+
+    // like in jq, the UTC offset is ignored
+    let f = r#"strptime("%Y-%m-%dT%H:%M:%S%z")"#;
+    let bdt = json!([2015, 2, 5, 23, 51, 47, 4, 63]);
+    give(json!("2015-03-05T23:51:47+0100"), f, bdt);
+}
+
+yields!(
+    strptime_mismatch,
+    r#""2015-03-05" | try strptime("%Y-%m-%dT%H:%M:%SZ") catch ."#,
+    r#"date "2015-03-05" does not match format "%Y-%m-%dT%H:%M:%SZ""#
+);
+yields!(
+    strptime_trailing,
+    r#""2015-03-05 23:51" | try strptime("%Y-%m-%d") catch -1"#,
+    -1
+);
+yields!(
+    strftime_invalid,
+    r#"0 | try strftime("%Q") catch ."#,
+    "invalid format %Q"
+);
+yields!(
+    strftime_gmtime,
+    r#"1700000000 | gmtime | strftime("%Y-%m-%d")"#,
+    "2023-11-14"
+);
+yields!(
+    strftime_malformed,
+    r#"[1, 2] | try strftime("%Y") catch ."#,
+    "cannot use [1,2] as broken down time"
+);
+
 #[test]
 fn startswith() {
     give(json!("foobar"), r#"startswith("")"#, json!(true));
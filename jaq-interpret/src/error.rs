@@ -23,12 +23,20 @@ pub enum Error<V = Val> {
     IndexOutOfBounds(isize),
     /// `0 |= .+1`
     PathExp,
+    /// `def f: 1 + f; f`
+    RecursionLimit,
 
     /// Tail-recursive call.
     ///
     /// This is used internally to execute tail-recursive filters.
     /// If this can be observed by users, then this is a bug.
     TailCall(crate::filter::TailCall<V>),
+
+    /// Break out of a label.
+    ///
+    /// This is used internally to execute `label $x | ... break $x`.
+    /// If this can be observed by users, then this is a bug.
+    Break(crate::filter::Break<V>),
 }
 
 /// Types and sets of types.
@@ -76,12 +84,14 @@ impl<V: ValT> fmt::Display for Error<V> {
                     write!(f, "{v}")
                 }
             }
-            Self::Type(v, ty) => write!(f, "cannot use {v} as {ty}"),
-            Self::MathOp(l, op, r) => write!(f, "cannot calculate {l} {op} {r}"),
-            Self::Index(v, i) => write!(f, "cannot index {v} with {i}"),
+            // the alternate form distinguishes floats from integers, such as `1.0` from `1`
+            Self::Type(v, ty) => write!(f, "cannot use {v:#} as {ty}"),
+            Self::MathOp(l, op, r) => write!(f, "cannot calculate {l:#} {op} {r:#}"),
+            Self::Index(v, i) => write!(f, "cannot index {v:#} with {i:#}"),
             Self::IndexOutOfBounds(i) => write!(f, "index {i} is out of bounds"),
             Self::PathExp => write!(f, "invalid path expression"),
-            Self::TailCall(_) => panic!(),
+            Self::RecursionLimit => write!(f, "recursion limit exceeded"),
+            Self::TailCall(_) | Self::Break(_) => panic!(),
         }
     }
 }
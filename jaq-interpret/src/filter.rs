@@ -1,5 +1,6 @@
 use crate::box_iter::{box_once, flat_map_with, map_with, BoxIter};
-use crate::results::{fold, recurse, then, Fold, Results};
+use crate::path::{ValPath, ValPathR2s};
+use crate::results::{fold, fold_extract, recurse, then, Fold, Results};
 use crate::val::{Val, ValR2, ValR2s, ValT};
 use crate::{rc_lazy_list, Bind, Ctx, Error};
 use alloc::{boxed::Box, string::String, vec::Vec};
@@ -52,6 +53,10 @@ pub enum CallTyp {
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct TailCall<V>(Id, crate::Vars<V>, V);
 
+/// Signal to stop the outputs of the label that is bound by the given variables.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Break<V>(pub(crate) crate::Vars<V>);
+
 #[derive(Clone, Debug)]
 pub(crate) struct Call {
     pub id: Id,
@@ -108,7 +113,14 @@ pub(crate) enum Ast {
     /// | ...
     /// | ., (xn as $x | f)...)
     /// ~~~
-    Fold(FoldType, Id, Id, Id),
+    ///
+    /// The last field is the extractor of `foreach xs as $x (init; f; extract)`.
+    Fold(FoldType, Id, Id, Id, Option<Id>),
+
+    /// `label $x | f`, where `f` may contain `break $x`
+    Label(Id),
+    /// `break $x`, where `$x` is the variable index of the label
+    Break(usize),
 
     Path(Id, crate::path::Path<Id>),
 
@@ -169,12 +181,15 @@ where
 }
 
 type Cv<'c, V = Val> = (Ctx<'c, V>, V);
+type CvPath<'c, V = Val> = (Ctx<'c, V>, ValPath<V>);
 
 /// A filter which is implemented using function pointers.
 #[derive(Clone)]
 pub struct Native<V = Val> {
     run: RunPtr<V>,
-    update: UpdatePtr<V>,
+    /// if this is not given, updates are performed on the output of `paths`
+    update: Option<UpdatePtr<V>>,
+    paths: PathsPtr<V>,
 }
 
 /// Run function pointer.
@@ -182,17 +197,35 @@ pub type RunPtr<V = Val> = for<'a> fn(Args<'a, V>, Cv<'a, V>) -> ValR2s<'a, V>;
 /// Update function pointer.
 pub type UpdatePtr<V = Val> =
     for<'a> fn(Args<'a, V>, Cv<'a, V>, Box<dyn Update<'a, V> + 'a>) -> ValR2s<'a, V>;
+/// Paths function pointer.
+pub type PathsPtr<V = Val> = for<'a> fn(Args<'a, V>, CvPath<'a, V>) -> ValPathR2s<'a, V>;
 
 impl<V> Native<V> {
     /// Create a native filter from a run function, without support for updates.
     pub const fn new(run: RunPtr<V>) -> Self {
-        Self::with_update(run, |_, _, _| box_once(Err(Error::PathExp)))
+        let paths: PathsPtr<V> = |_, _| box_once(Err(Error::PathExp));
+        Self {
+            run,
+            update: None,
+            paths,
+        }
     }
 
     /// Create a native filter from a run function and an update function (used for `filter |= ...`).
     // TODO for v2.0: remove this
     pub const fn with_update(run: RunPtr<V>, update: UpdatePtr<V>) -> Self {
-        Self { run, update }
+        let paths: PathsPtr<V> = |_, _| box_once(Err(Error::PathExp));
+        let update = Some(update);
+        Self { run, update, paths }
+    }
+
+    /// Add a paths function (used for `path(filter)`).
+    ///
+    /// If the filter has no update function, then
+    /// this is also used for `filter |= ...`, by updating the values at the returned paths.
+    pub const fn with_paths(self, paths: PathsPtr<V>) -> Self {
+        let (run, update) = (self.run, self.update);
+        Self { run, update, paths }
     }
 }
 
@@ -234,6 +267,10 @@ impl<'a, V: ValT> FilterT<'a, V> for &'a Owned<V> {
     fn update(self, cv: Cv<'a, V>, f: Box<dyn Update<'a, V> + 'a>) -> ValR2s<'a, V> {
         Ref(self.0, &self.1).update(cv, f)
     }
+
+    fn paths(self, cv: CvPath<'a, V>) -> ValPathR2s<'a, V> {
+        Ref(self.0, &self.1).paths(cv)
+    }
 }
 
 impl<'a, V: ValT> FilterT<'a, V> for Ref<'a, V> {
@@ -258,7 +295,11 @@ impl<'a, V: ValT> FilterT<'a, V> for Ref<'a, V> {
             }
             Ast::Try(f, c) => Box::new(w(f).run((cv.0.clone(), cv.1)).flat_map(move |y| {
                 y.map_or_else(
-                    |e| w(c).run((cv.0.clone(), e.as_val())),
+                    |e| match e {
+                        // `try` does not catch `break`
+                        Error::Break(_) => box_once(Err(e)),
+                        e => w(c).run((cv.0.clone(), e.as_val())),
+                    },
                     |v| box_once(Ok(v)),
                 )
             })),
@@ -274,9 +315,11 @@ impl<'a, V: ValT> FilterT<'a, V> for Ref<'a, V> {
 
             Ast::Comma(l, r) => Box::new(w(l).run(cv.clone()).chain(w(r).run(cv))),
             Ast::Alt(l, r) => {
-                let mut l = w(l)
-                    .run(cv.clone())
-                    .filter(|v| v.as_ref().map_or(true, |v| v.as_bool()));
+                let mut l = w(l).run(cv.clone()).filter(|y| match y {
+                    Ok(v) => v.as_bool(),
+                    // errors are treated like no output, but `break` is not an error
+                    Err(e) => matches!(e, Error::Break(_)),
+                });
                 match l.next() {
                     Some(head) => Box::new(once(head).chain(l)),
                     None => w(r).run(cv),
@@ -322,28 +365,48 @@ impl<'a, V: ValT> FilterT<'a, V> for Ref<'a, V> {
                 Self::cartesian(w(l), w(r), cv).map(|(x, y)| Ok(V::from(op.run(&x?, &y?)))),
             ),
 
-            Ast::Fold(typ, xs, init, f) => {
+            Ast::Fold(typ, xs, init, f, extract) => {
                 let xs = rc_lazy_list::List::from_iter(w(xs).run(cv.clone()));
                 let init = w(init).run(cv.clone());
+                let ctx = cv.0.clone();
                 let f = move |x, v| w(f).run((cv.0.clone().cons_var(x), v));
                 use Fold::{Input, Output};
-                match typ {
-                    FoldType::Reduce => Box::new(fold(false, xs, Output(init), f)),
-                    FoldType::For => Box::new(fold(true, xs, Output(init), f)),
-                    FoldType::Foreach => flat_map_with(init, xs, move |i, xs| {
+                match (typ, extract) {
+                    (FoldType::Reduce, _) => Box::new(fold(false, xs, Output(init), f)),
+                    (FoldType::For, _) => Box::new(fold(true, xs, Output(init), f)),
+                    (FoldType::Foreach, None) => flat_map_with(init, xs, move |i, xs| {
                         then(i, |i| Box::new(fold(true, xs, Input(i), f.clone())))
                     }),
+                    (FoldType::Foreach, Some(e)) => {
+                        let e = move |x, v| w(e).run((ctx.clone().cons_var(x), v));
+                        flat_map_with(init, xs, move |i, xs| {
+                            then(i, |i| Box::new(fold_extract(xs, i, f.clone(), e.clone())))
+                        })
+                    }
                 }
             }
 
+            Ast::Label(f) => {
+                let ctx = cv.0.cons_label();
+                let ys = w(f).run((ctx.clone(), cv.1));
+                Box::new(ys.map_while(move |y| match y {
+                    Err(Error::Break(b)) if ctx.stops(&b) => None,
+                    y => Some(y),
+                }))
+            }
+            Ast::Break(v) => box_once(Err(cv.0.break_(*v))),
+
             Ast::Var(v) => match cv.0.vars.get(*v).unwrap() {
                 Bind::Var(v) => box_once(Ok(v.clone())),
                 Bind::Fun(f) => w(&f.0).run((cv.0.with_vars(f.1.clone()), cv.1)),
             },
             Ast::Call(call) => {
                 let def = w(&call.id);
-                let ctx = cv.0.clone().skip_vars(call.skip);
-                let inputs = cv.0.inputs;
+                let ctx = match cv.0.clone().skip_vars(call.skip).enter() {
+                    Ok(ctx) => ctx,
+                    Err(e) => return box_once(Err(e)),
+                };
+                let (inputs, depth) = (cv.0.inputs, ctx.depth);
                 let cvs = bind_vars(call.args.iter().map(move |a| a.as_ref().map(w)), ctx, cv);
                 match call.typ {
                     CallTyp::Normal => run_cvs(def, cvs),
@@ -351,7 +414,12 @@ impl<'a, V: ValT> FilterT<'a, V> for Ref<'a, V> {
                         Vec::from([run_cvs(def, cvs)]),
                         move |r| match r {
                             Err(Error::TailCall(TailCall(id, vars, v))) if id == call.id => {
-                                ControlFlow::Continue(def.run((Ctx { inputs, vars }, v)))
+                                let ctx = Ctx {
+                                    inputs,
+                                    vars,
+                                    depth,
+                                };
+                                ControlFlow::Continue(def.run((ctx, v)))
                             }
                             Ok(_) | Err(_) => ControlFlow::Break(r),
                         },
@@ -375,10 +443,12 @@ impl<'a, V: ValT> FilterT<'a, V> for Ref<'a, V> {
             Ast::Array(_) | Ast::ObjEmpty | Ast::ObjSingle(..) => err,
             Ast::Neg(_) | Ast::Logic(..) | Ast::Math(..) | Ast::Ord(..) => err,
             Ast::Update(..) | Ast::UpdateMath(..) | Ast::Assign(..) => err,
+            Ast::Break(v) => box_once(Err(cv.0.break_(*v))),
 
-            // these are up for grabs to implement :)
-            Ast::Try(..) | Ast::Alt(..) | Ast::Fold(..) => {
-                unimplemented!("updating with this operator is not supported yet")
+            // update the values at the paths of the outputs
+            Ast::Try(..) | Ast::Alt(..) | Ast::Fold(..) | Ast::Label(_) => {
+                let paths = self.paths((cv.0, (cv.1.clone(), Default::default())));
+                box_once(crate::path::update_paths(cv.1, paths, f))
             }
 
             Ast::Id => f(cv.1),
@@ -417,12 +487,146 @@ impl<'a, V: ValT> FilterT<'a, V> for Ref<'a, V> {
             Ast::Call(call) => {
                 let def = w(&call.id);
                 let init = cv.1.clone();
-                let ctx = cv.0.clone().skip_vars(call.skip);
+                let ctx = match cv.0.clone().skip_vars(call.skip).enter() {
+                    Ok(ctx) => ctx,
+                    Err(e) => return box_once(Err(e)),
+                };
                 let cvs = bind_vars(call.args.iter().map(move |a| a.as_ref().map(w)), ctx, cv);
                 reduce(cvs, init, move |cv, v| def.update((cv.0, v), f.clone()))
             }
 
-            Ast::Native(id, args) => (self.1.natives[*id].update)(Args(args, self.1), cv, f),
+            Ast::Native(id, args) => {
+                let native = &self.1.natives[*id];
+                let args = Args(args, self.1);
+                match native.update {
+                    Some(update) => update(args, cv, f),
+                    None => {
+                        let paths =
+                            (native.paths)(args, (cv.0, (cv.1.clone(), Default::default())));
+                        box_once(crate::path::update_paths(cv.1, paths, f))
+                    }
+                }
+            }
+        }
+    }
+
+    fn paths(self, cv: CvPath<'a, V>) -> ValPathR2s<'a, V> {
+        let err = box_once(Err(Error::PathExp));
+        let w = move |id: &Id| Ref(*id, self.1);
+        match &self.1.defs[self.0 .0] {
+            Ast::ToString => err,
+            Ast::Int(_) | Ast::Num(_) | Ast::Str(_) => err,
+            Ast::Array(_) | Ast::ObjEmpty | Ast::ObjSingle(..) => err,
+            Ast::Neg(_) | Ast::Logic(..) | Ast::Math(..) | Ast::Ord(..) => err,
+            Ast::Update(..) | Ast::UpdateMath(..) | Ast::Assign(..) => err,
+
+            Ast::Id => box_once(Ok(cv.1)),
+            Ast::Try(f, c) => Box::new(w(f).paths((cv.0.clone(), cv.1)).flat_map(move |y| {
+                y.map_or_else(
+                    // the outputs of `c` do not have paths
+                    |e| -> ValPathR2s<V> {
+                        if let Error::Break(_) = e {
+                            return box_once(Err(e));
+                        }
+                        let ys = w(c).run((cv.0.clone(), e.as_val()));
+                        Box::new(ys.map(|y| y.and(Err(Error::PathExp))))
+                    },
+                    |vp| box_once(Ok(vp)),
+                )
+            })),
+            Ast::Pipe(l, false, r) => {
+                let l = w(l).paths((cv.0.clone(), cv.1));
+                flat_map_with(l, cv.0, move |y, ctx| then(y, |y| w(r).paths((ctx, y))))
+            }
+            Ast::Pipe(l, true, r) => {
+                let l = w(l).run((cv.0.clone(), cv.1 .0.clone()));
+                flat_map_with(l, cv, move |y, cv| {
+                    then(y, |y| w(r).paths((cv.0.cons_var(y), cv.1)))
+                })
+            }
+            Ast::Comma(l, r) => Box::new(w(l).paths(cv.clone()).chain(w(r).paths(cv))),
+            Ast::Alt(l, r) => {
+                let mut l = w(l).paths(cv.clone()).filter(|y| match y {
+                    Ok((v, _)) => v.as_bool(),
+                    Err(e) => matches!(e, Error::PathExp | Error::Break(_)),
+                });
+                match l.next() {
+                    Some(head) => Box::new(core::iter::once(head).chain(l)),
+                    None => w(r).paths(cv),
+                }
+            }
+            Ast::Ite(if_, then_, else_) => {
+                let if_ = w(if_).run((cv.0.clone(), cv.1 .0.clone()));
+                flat_map_with(if_, cv, move |v, cv| {
+                    then(v, |v| w(if v.as_bool() { then_ } else { else_ }).paths(cv))
+                })
+            }
+            Ast::Path(f, path) => {
+                let path = path.map_ref(|i| {
+                    let cv = (cv.0.clone(), cv.1 .0.clone());
+                    crate::into_iter::collect_if_once(move || w(i).run(cv))
+                });
+                flat_map_with(w(f).paths(cv), path, |y, path| {
+                    then(y, |y| {
+                        flat_map_with(path.explode(), y, |path, y| {
+                            then(path, |path| path.paths(y))
+                        })
+                    })
+                })
+            }
+
+            Ast::Fold(typ, xs, init, f, extract) => {
+                let xs = rc_lazy_list::List::from_iter(w(xs).run((cv.0.clone(), cv.1 .0.clone())));
+                let init = w(init).paths(cv.clone());
+                let ctx = cv.0.clone();
+                let f = move |x, vp| w(f).paths((cv.0.clone().cons_var(x), vp));
+                use Fold::{Input, Output};
+                match (typ, extract) {
+                    (FoldType::Reduce, _) => Box::new(fold(false, xs, Output(init), f)),
+                    (FoldType::For, _) => Box::new(fold(true, xs, Output(init), f)),
+                    (FoldType::Foreach, None) => flat_map_with(init, xs, move |i, xs| {
+                        then(i, |i| Box::new(fold(true, xs, Input(i), f.clone())))
+                    }),
+                    (FoldType::Foreach, Some(e)) => {
+                        let e = move |x, vp| w(e).paths((ctx.clone().cons_var(x), vp));
+                        flat_map_with(init, xs, move |i, xs| {
+                            then(i, |i| Box::new(fold_extract(xs, i, f.clone(), e.clone())))
+                        })
+                    }
+                }
+            }
+
+            Ast::Label(f) => {
+                let ctx = cv.0.cons_label();
+                let ys = w(f).paths((ctx.clone(), cv.1));
+                Box::new(ys.map_while(move |y| match y {
+                    Err(Error::Break(b)) if ctx.stops(&b) => None,
+                    y => Some(y),
+                }))
+            }
+            Ast::Break(v) => box_once(Err(cv.0.break_(*v))),
+
+            Ast::Var(v) => match cv.0.vars.get(*v).unwrap() {
+                Bind::Var(_) => err,
+                Bind::Fun(f) => w(&f.0).paths((cv.0.with_vars(f.1.clone()), cv.1)),
+            },
+            // tail calls are not optimised here, because
+            // a tail call exception cannot carry the current path
+            Ast::Call(call) => {
+                let def = w(&call.id);
+                let ctx = match cv.0.clone().skip_vars(call.skip).enter() {
+                    Ok(ctx) => ctx,
+                    Err(e) => return box_once(Err(e)),
+                };
+                let (v, p) = cv.1;
+                let args = call.args.iter().map(move |a| a.as_ref().map(w));
+                let cvs = bind_vars(args, ctx, (cv.0, v));
+                flat_map_with(cvs, p, move |cv, p| {
+                    then(cv, |cv| def.paths((cv.0, (cv.1, p))))
+                })
+            }
+
+            Ast::Native(id, args) => (self.1.natives[*id].paths)(Args(args, self.1), cv),
         }
     }
 }
@@ -439,6 +643,27 @@ pub trait FilterT<'a, V: ValT = Val>: Clone + 'a {
     /// `p.update((c, v), f)` returns the output of `v | p |= f` in the context `c`.
     fn update(self, cv: Cv<'a, V>, f: Box<dyn Update<'a, V> + 'a>) -> ValR2s<'a, V>;
 
+    /// `f.paths((c, (v, p)))` returns the outputs of `v | f` in the context `c`,
+    /// together with their paths, provided that `p` is the path of `v`.
+    ///
+    /// By default, this fails, meaning that `f` is not a path expression.
+    fn paths(self, cv: CvPath<'a, V>) -> ValPathR2s<'a, V> {
+        let _ = cv;
+        box_once(Err(Error::PathExp))
+    }
+
+    /// `f.path((c, v))` returns the paths of the outputs of `v | f` in the context `c`.
+    ///
+    /// This is used by `path(f)`.
+    fn path(self, cv: Cv<'a, V>) -> ValR2s<'a, V> {
+        let paths = self.paths((cv.0, (cv.1, Default::default())));
+        Box::new(paths.map(|y| {
+            let mut path: Vec<_> = y?.1.iter().cloned().collect();
+            path.reverse();
+            Ok(path.into_iter().collect())
+        }))
+    }
+
     /// For every value `v` returned by `self.run(cv)`, call `f(cv, v)` and return all results.
     ///
     /// This has a special optimisation for the case where only a single `v` is returned.
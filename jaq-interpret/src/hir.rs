@@ -9,7 +9,7 @@ use crate::Bind;
 use alloc::{boxed::Box, string::String, vec::Vec};
 use core::fmt;
 use jaq_syn::filter::{BinaryOp, Filter as Expr, Fold};
-use jaq_syn::{Arg, Spanned};
+use jaq_syn::Spanned;
 
 pub type Filter = jaq_syn::filter::Filter<Call, VarIdx, Num>;
 pub type Main = jaq_syn::Main<Filter>;
@@ -35,31 +35,75 @@ pub enum Num {
 }
 
 impl Num {
-    fn parse(n: String) -> Result<Self, String> {
-        if n.contains(['.', 'e', 'E']) {
-            Ok(Self::Num(n))
-        } else {
-            n.parse().map(Num::Int).map_err(|_| n)
+    /// Parse a number literal, keeping it as string unless it is a machine-sized integer.
+    ///
+    /// This preserves the literal decimal representation of large integers
+    /// such as `12345678901234567890`, like when parsing JSON input.
+    fn parse(n: String) -> Self {
+        if !n.contains(['.', 'e', 'E']) {
+            if let Ok(i) = n.parse() {
+                return Self::Int(i);
+            }
         }
+        Self::Num(n)
     }
 }
 
+/// Error that occurs when resolving names.
 pub enum Error {
-    Undefined(Arg),
-    Num(String),
+    /// undefined variable
+    Var(String),
+    /// undefined filter with name and arity, together with a similar accessible filter
+    Filter((String, usize), Option<(String, usize)>),
+    /// undefined label
+    Label(String),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Self::Undefined(Bind::Var(_)) => "undefined variable",
-            Self::Undefined(Bind::Fun(_)) => "undefined filter",
-            Self::Num(_) => "cannot interpret as machine-size integer",
+            Self::Var(_) => "undefined variable".fmt(f),
+            Self::Filter((name, arity), similar) => {
+                write!(f, "undefined filter `{name}/{arity}`")?;
+                if let Some((name, arity)) = similar {
+                    write!(f, ", did you mean `{name}/{arity}`?")?;
+                }
+                Ok(())
+            }
+            Self::Label(_) => "undefined label".fmt(f),
         }
-        .fmt(f)
     }
 }
 
+/// Return the Levenshtein distance between two strings,
+/// that is, the minimal number of characters to insert, delete, or replace
+/// in order to obtain one string from the other.
+fn distance(x: &str, y: &str) -> usize {
+    let y: Vec<char> = y.chars().collect();
+    let mut row: Vec<usize> = (0..=y.len()).collect();
+    for (i, cx) in x.chars().enumerate() {
+        let mut diag = core::mem::replace(&mut row[0], i + 1);
+        for (j, cy) in y.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if cx == *cy {
+                diag
+            } else {
+                1 + diag.min(above).min(row[j])
+            };
+            diag = above;
+        }
+    }
+    row[y.len()]
+}
+
+/// Return the name of the variable that a label is bound to.
+///
+/// This name cannot be written by users,
+/// so labels and variables do not shadow each other.
+fn label_var(x: &str) -> String {
+    alloc::format!("*label-{x}")
+}
+
 struct Callable {
     typ: Relative,
     sig: jaq_syn::Call,
@@ -116,6 +160,29 @@ impl Ctx {
             .map(Call::Native)
     }
 
+    /// Return an accessible filter that is similar to the given undefined one.
+    ///
+    /// We prefer filters with the same name (but different arity)
+    /// over filters whose names differ by a few characters.
+    fn similar(&self, name: &str, arity: usize) -> Option<(String, usize)> {
+        let args = self.callable.iter().filter(|c| c.typ == Relative::Parent);
+        let args = args.flat_map(|c| c.sig.args.iter().filter_map(|a| a.get_filter()));
+        let defs = self
+            .callable
+            .iter()
+            .map(|c| (&*c.sig.name, c.sig.args.len()));
+        let native = self.native.iter().map(|(name, arity)| (&**name, *arity));
+        // names of imported filters contain ':', and cannot be written by users
+        let sigs = args.map(|a| (a, 0)).chain(defs).chain(native);
+        let sigs = sigs.filter(|(name, _)| !name.contains(':'));
+
+        let key =
+            |(name_, arity_): &(&str, usize)| (distance(name, name_), arity.abs_diff(*arity_));
+        let (name_, arity_) = sigs.min_by_key(key)?;
+        let max = core::cmp::max(1, name.chars().count() / 3);
+        (distance(name, name_) <= max).then(|| (name_.into(), arity_))
+    }
+
     pub fn main(&mut self, main: jaq_syn::Main) -> Main {
         let defs: Vec<_> = main.defs.into_iter().map(|def| self.def(def)).collect();
         assert!(self.vars.is_empty());
@@ -141,23 +208,24 @@ impl Ctx {
 
     fn expr(&mut self, f: Spanned<Expr>) -> Spanned<Filter> {
         let get = |ctx: &mut Self, f| Box::new(ctx.expr(f));
-        let undefined = |arg| (Error::Undefined(arg), f.1.clone());
         let result = match f.0 {
             Expr::Call(name, args) => {
                 let args: Vec<_> = args.into_iter().map(|arg| self.expr(arg)).collect();
 
-                self.resolve_call(&name, args.len()).map_or_else(
-                    || {
-                        self.errs.push(undefined(Arg::new_filter(name)));
+                match self.resolve_call(&name, args.len()) {
+                    Some(call) => Expr::Call(call, args),
+                    None => {
+                        let similar = self.similar(&name, args.len());
+                        let err = Error::Filter((name, args.len()), similar);
+                        self.errs.push((err, f.1.clone()));
                         Expr::Id
-                    },
-                    |call| Expr::Call(call, args),
-                )
+                    }
+                }
             }
             Expr::Var(v) => {
                 let idx = self.bound().rev().position(|i| i == Bind::Var(&v));
                 Expr::Var(idx.unwrap_or_else(|| {
-                    self.errs.push(undefined(Arg::Var(v)));
+                    self.errs.push((Error::Var(v), f.1.clone()));
                     0
                 }))
             }
@@ -168,18 +236,46 @@ impl Ctx {
                 assert!(self.vars.pop().as_ref() == Some(&x));
                 Expr::Binary(l, BinaryOp::Pipe(Some(x)), r)
             }
-            Expr::Fold(typ, Fold { xs, x, init, f }) => {
+            Expr::Fold(
+                typ,
+                Fold {
+                    xs,
+                    x,
+                    init,
+                    f,
+                    extract,
+                },
+            ) => {
                 let (xs, init) = (get(self, *xs), get(self, *init));
                 self.vars.push(x.clone());
                 let f = get(self, *f);
+                let extract = extract.map(|e| get(self, *e));
                 assert!(self.vars.pop().as_ref() == Some(&x));
-                Expr::Fold(typ, Fold { xs, x, init, f })
+                let fold = Fold {
+                    xs,
+                    x,
+                    init,
+                    f,
+                    extract,
+                };
+                Expr::Fold(typ, fold)
+            }
+            Expr::Label(x, f) => {
+                self.vars.push(label_var(&x));
+                let f = get(self, *f);
+                assert!(self.vars.pop() == Some(label_var(&x)));
+                Expr::Label(x, f)
+            }
+            Expr::Break(x) => {
+                let var = label_var(&x);
+                let idx = self.bound().rev().position(|i| i == Bind::Var(&var));
+                Expr::Break(idx.unwrap_or_else(|| {
+                    self.errs.push((Error::Label(x), f.1.clone()));
+                    0
+                }))
             }
             Expr::Id => Expr::Id,
-            Expr::Num(n) => Expr::Num(Num::parse(n).unwrap_or_else(|n| {
-                self.errs.push((Error::Num(n), f.1.clone()));
-                Num::Int(0)
-            })),
+            Expr::Num(n) => Expr::Num(Num::parse(n)),
             Expr::Str(s) => Expr::Str(Box::new((*s).map(|f| self.expr(f)))),
             Expr::Array(a) => Expr::Array(a.map(|a| get(self, *a))),
             Expr::Object(o) => {
@@ -65,7 +65,7 @@ mod val;
 mod exn;
 
 pub use error::Error;
-pub use filter::{Args, FilterT, Native, Owned as Filter, RunPtr, UpdatePtr};
+pub use filter::{Args, FilterT, Native, Owned as Filter, PathsPtr, RunPtr, UpdatePtr};
 pub use rc_iter::RcIter;
 pub use val::{Val, ValR, ValRs, ValT};
 
@@ -90,13 +90,47 @@ impl<V> Vars<V> {
 pub struct Ctx<'a, V = Val> {
     vars: Vars<V>,
     inputs: &'a Inputs<'a, V>,
+    /// number of nested filter calls that may still be made
+    depth: usize,
 }
 
+/// Default maximal number of nested filter calls, see [`Ctx::with_recursion_limit`].
+///
+/// In release builds, a single call takes up to about 4 KiB of stack,
+/// so filters that stay within this limit need up to about 40 MiB of stack.
+/// Because the main thread usually has a smaller stack,
+/// you should run filters on a thread with a sufficiently large stack,
+/// or set a lower limit.
+pub const RECURSION_LIMIT: usize = 10_000;
+
 impl<'a, V> Ctx<'a, V> {
     /// Construct a context.
     pub fn new(vars: impl IntoIterator<Item = V>, inputs: &'a Inputs<'a, V>) -> Self {
         let vars = Vars(RcList::new().extend(vars.into_iter().map(Bind::Var)));
-        Self { vars, inputs }
+        let depth = RECURSION_LIMIT;
+        Self {
+            vars,
+            inputs,
+            depth,
+        }
+    }
+
+    /// Set the maximal number of nested filter calls.
+    ///
+    /// When a filter call would exceed this limit,
+    /// it yields a "recursion limit exceeded" error instead.
+    /// This prevents deeply recursive filters from overflowing the stack.
+    /// Tail calls do not count towards the limit.
+    /// By default, the limit is [`RECURSION_LIMIT`].
+    pub fn with_recursion_limit(mut self, limit: usize) -> Self {
+        self.depth = limit;
+        self
+    }
+
+    /// Enter a filter call, failing if this exceeds the recursion limit.
+    fn enter(mut self) -> Result<Self, Error<V>> {
+        self.depth = self.depth.checked_sub(1).ok_or(Error::RecursionLimit)?;
+        Ok(self)
     }
 
     /// Add a new variable binding.
@@ -111,6 +145,27 @@ impl<'a, V> Ctx<'a, V> {
         self
     }
 
+    /// Add a new label binding.
+    ///
+    /// The value bound to the label is never read;
+    /// only the identity of the binding matters.
+    pub(crate) fn cons_label(self) -> Self
+    where
+        V: From<bool>,
+    {
+        self.cons_var(V::from(false))
+    }
+
+    /// Return an error that stops the outputs of the label with the given variable index.
+    fn break_(&self, v: usize) -> Error<V> {
+        Error::Break(filter::Break(Vars(self.vars.0.skip(v).clone())))
+    }
+
+    /// Return true if the given break stops the most recently bound label.
+    fn stops(&self, filter::Break(vars): &filter::Break<V>) -> bool {
+        self.vars.0.ptr_eq(&vars.0)
+    }
+
     /// Remove the `skip` most recent variable bindings.
     fn skip_vars(mut self, skip: usize) -> Self {
         if skip > 0 {
@@ -120,8 +175,12 @@ impl<'a, V> Ctx<'a, V> {
     }
 
     fn with_vars(&self, vars: Vars<V>) -> Self {
-        let inputs = self.inputs;
-        Self { vars, inputs }
+        let (inputs, depth) = (self.inputs, self.depth);
+        Self {
+            vars,
+            inputs,
+            depth,
+        }
     }
 
     /// Return remaining input values.
@@ -94,6 +94,7 @@ impl Ctx {
             self.def(def);
         });
         let body = self.filter(main.body);
+        let body = self.fold(body);
 
         self.callable.drain(self.callable.len() - defs_len..);
 
@@ -115,11 +116,53 @@ impl Ctx {
     }
 
     fn id_of_ast(&mut self, f: filter::Ast) -> AbsId {
+        let f = self.fold(f);
         let len = self.defs.len();
         self.defs.push(f);
         AbsId(len)
     }
 
+    /// Simplify a filter whose children have already been simplified.
+    ///
+    /// This folds arithmetic on literals and removes identities from pipes.
+    /// If folding would change the outcome (e.g. on integer overflow),
+    /// the filter is returned unchanged.
+    /// When a filter is folded to a literal, its children are removed,
+    /// so that `1 + 2` and `3` yield the same definitions.
+    fn fold(&mut self, f: Filter) -> Filter {
+        let def = |id: &AbsId| &self.defs[id.0];
+        let folded = match &f {
+            Filter::Pipe(l, false, r) if matches!(def(l), Filter::Id) => Some(def(r).clone()),
+            Filter::Pipe(l, false, r) if matches!(def(r), Filter::Id) => Some(def(l).clone()),
+            Filter::Neg(x) => match def(x) {
+                Filter::Int(x) => x.checked_neg().map(Filter::Int),
+                _ => None,
+            },
+            Filter::Math(l, op, r) => match (def(l), op, def(r)) {
+                (Filter::Int(x), MathOp::Add, Filter::Int(y)) => x.checked_add(*y).map(Filter::Int),
+                (Filter::Int(x), MathOp::Sub, Filter::Int(y)) => x.checked_sub(*y).map(Filter::Int),
+                (Filter::Int(x), MathOp::Mul, Filter::Int(y)) => x.checked_mul(*y).map(Filter::Int),
+                (Filter::Str(x), MathOp::Add, Filter::Str(y)) => Some(Filter::Str(x.clone() + y)),
+                _ => None,
+            },
+            // literals other than `null` and `false` are always true
+            Filter::Alt(l, _) => match def(l) {
+                lit @ (Filter::Int(_) | Filter::Num(_) | Filter::Str(_)) => Some(lit.clone()),
+                _ => None,
+            },
+            _ => None,
+        };
+        let folded = match folded {
+            Some(folded) => folded,
+            None => return f,
+        };
+        // children are defined after one another, directly before their parent
+        if let Filter::Neg(x) | Filter::Math(x, ..) | Filter::Alt(x, _) = f {
+            self.defs.truncate(x.0);
+        }
+        folded
+    }
+
     fn get(&mut self, f: Spanned<mir::Filter>) -> AbsId {
         let f = self.filter(f);
         self.id_of_ast(f)
@@ -192,10 +235,23 @@ impl Ctx {
                 }
             }
 
-            Expr::Fold(typ, Fold { xs, init, f, .. }) => {
-                Filter::Fold(typ, self.get(*xs), self.get(*init), self.get(*f))
+            Expr::Fold(
+                typ,
+                Fold {
+                    xs,
+                    init,
+                    f,
+                    extract,
+                    ..
+                },
+            ) => {
+                let (xs, init, f) = (self.get(*xs), self.get(*init), self.get(*f));
+                Filter::Fold(typ, xs, init, f, extract.map(|e| self.get(*e)))
             }
 
+            Expr::Label(_, f) => Filter::Label(self.get(*f)),
+            Expr::Break(v) => Filter::Break(v),
+
             Expr::Id => Filter::Id,
             Expr::Num(hir::Num::Num(n)) => Filter::Num(n),
             Expr::Num(hir::Num::Int(i)) => Filter::Int(i),
@@ -5,7 +5,7 @@
 
 use crate::hir::{self, ArgIdx, NativeId, Num, RelId, VarIdx};
 use alloc::{boxed::Box, vec::Vec};
-use jaq_syn::filter::{BinaryOp, Filter as Expr, Fold};
+use jaq_syn::filter::{BinaryOp, Filter as Expr};
 use jaq_syn::Spanned;
 
 pub type Filter = jaq_syn::filter::Filter<Call, VarIdx, Num>;
@@ -126,12 +126,11 @@ impl Ctx {
                 Expr::Binary(get(self, *l, notr()), op, get(self, *r, notr()))
             }
 
-            Expr::Fold(typ, Fold { xs, x, init, f }) => {
-                let xs = get(self, *xs, notr());
-                let init = get(self, *init, notr());
-                let f = get(self, *f, notr());
-                Expr::Fold(typ, Fold { xs, x, init, f })
-            }
+            Expr::Fold(typ, fold) => Expr::Fold(typ, fold.map(|f| get(self, *f, notr()))),
+            // a tail call must not escape its label,
+            // because a `break` could then not be caught anymore
+            Expr::Label(x, f) => Expr::Label(x, get(self, *f, notr())),
+            Expr::Break(x) => Expr::Break(x),
             Expr::Id => Expr::Id,
             Expr::Recurse => Expr::Recurse,
             Expr::Num(n) => Expr::Num(n),
@@ -1,9 +1,19 @@
 use crate::box_iter::{box_once, flat_map_with, map_with, BoxIter};
-use crate::results::then;
+use crate::results::{then, Results};
 use crate::val::{ValR2, ValT};
+use crate::{Error, RcList};
+use alloc::string::ToString;
 use alloc::{boxed::Box, vec::Vec};
 pub use jaq_syn::path::Opt;
 
+/// Value together with the path that leads to it.
+///
+/// The path is stored in reverse order, such that
+/// extending it does not require copying it.
+pub type ValPath<V> = (V, RcList<V>);
+/// A stream of value-path results.
+pub type ValPathR2s<'a, V> = Results<'a, ValPath<V>, Error<V>>;
+
 #[derive(Clone, Debug)]
 pub struct Path<F>(pub Vec<(Part<F>, Opt)>);
 
@@ -45,6 +55,10 @@ impl<'a, V: ValT + 'a> Path<V> {
         run(self.0.into_iter(), v)
     }
 
+    pub fn paths(self, vp: ValPath<V>) -> ValPathR2s<'a, V> {
+        paths(self.0.into_iter(), vp)
+    }
+
     pub fn update<F>(mut self, v: V, f: F) -> ValR2<V>
     where
         F: Fn(V) -> Box<dyn Iterator<Item = ValR2<V>> + 'a>,
@@ -58,6 +72,30 @@ impl<'a, V: ValT + 'a> Path<V> {
     }
 }
 
+/// Update the values at the given paths one after another, like jq does for `p |= f`
+/// when `p` is a filter such as `first(g)` or `g // h`.
+///
+/// The paths are obtained from `p` for the original value,
+/// so they are not adapted to previous updates.
+pub fn update_paths<'a, V: ValT + 'a, F>(v: V, mut paths: ValPathR2s<'a, V>, f: F) -> ValR2<V>
+where
+    F: Fn(V) -> Box<dyn Iterator<Item = ValR2<V>> + 'a>,
+{
+    paths.try_fold(v, |v, vp| {
+        let mut parts: Vec<_> = vp?
+            .1
+            .iter()
+            .map(|k| (Part::from_key(k), Opt::Essential))
+            .collect();
+        if parts.is_empty() {
+            return f(v.clone()).next().unwrap_or(Ok(v));
+        }
+        // paths are stored in reverse order
+        parts.reverse();
+        Path(parts).update(v, &f)
+    })
+}
+
 fn run<'a, V: ValT + 'a, I>(mut iter: I, val: V) -> Box<dyn Iterator<Item = ValR2<V>> + 'a>
 where
     I: Iterator<Item = (Part<V>, Opt)> + Clone + 'a,
@@ -71,6 +109,19 @@ where
     }
 }
 
+fn paths<'a, V: ValT + 'a, I>(mut iter: I, vp: ValPath<V>) -> ValPathR2s<'a, V>
+where
+    I: Iterator<Item = (Part<V>, Opt)> + Clone + 'a,
+{
+    if let Some((part, opt)) = iter.next() {
+        let essential = matches!(opt, Opt::Essential);
+        let ys = part.paths(vp).filter(move |v| essential || v.is_ok());
+        flat_map_with(ys, iter, move |vp, iter| then(vp, |vp| paths(iter, vp)))
+    } else {
+        box_once(Ok(vp))
+    }
+}
+
 fn update<'a, V: ValT, P, F>(mut iter: P, last: (Part<V>, Opt), v: V, f: &F) -> ValR2<V>
 where
     P: Iterator<Item = (Part<V>, Opt)> + Clone,
@@ -85,6 +136,20 @@ where
 }
 
 impl<'a, V: ValT + 'a> Part<V> {
+    /// Convert a key of a path, such as returned by `path(f)`, to a part of a path.
+    ///
+    /// Objects with the keys `start` and/or `end` yield ranges, everything else yields indices.
+    fn from_key(k: &V) -> Self {
+        let get = |s: &str| k.clone().index(&V::from(s.to_string()));
+        // `null` and `false` are the only values that are not true
+        let is_null = |v: &V| !v.as_bool() && *v != V::from(false);
+        let some = |v: V| (!is_null(&v)).then_some(v);
+        match (get("start"), get("end")) {
+            (Ok(from), Ok(upto)) if !is_null(k) => Self::Range(some(from), some(upto)),
+            _ => Self::Index(k.clone()),
+        }
+    }
+
     fn run(&self, v: V) -> impl Iterator<Item = ValR2<V>> + 'a {
         match self {
             Self::Index(idx) => box_once(v.index(idx)),
@@ -93,6 +158,29 @@ impl<'a, V: ValT + 'a> Part<V> {
         }
     }
 
+    fn paths(&self, (v, p): ValPath<V>) -> ValPathR2s<'a, V> {
+        // `null` and `false` are the only values that are not true
+        let is_null = |v: &V| !v.as_bool() && *v != V::from(false);
+        match self {
+            // unlike `.[k]`, this yields `null` for `null` input, like jq;
+            // that way, we can obtain paths to values that do not exist yet
+            Self::Index(idx) if is_null(&v) => box_once(Ok((v, p.cons(idx.clone())))),
+            Self::Index(idx) => box_once(v.index(idx).map(|v| (v, p.cons(idx.clone())))),
+            Self::Range(None, None) => Box::new(
+                v.key_values()
+                    .map(move |kv| kv.map(|(k, v)| (v, p.clone().cons(k)))),
+            ),
+            Self::Range(from, upto) => {
+                let v = v.range(from.as_ref()..upto.as_ref());
+                let key = |k: &str| V::from(k.to_string());
+                let start = from.iter().map(|from| (key("start"), from.clone()));
+                let end = upto.iter().map(|upto| (key("end"), upto.clone()));
+                let k = V::from_map(start.chain(end));
+                box_once(v.and_then(|v| Ok((v, p.cons(k?)))))
+            }
+        }
+    }
+
     fn update<F, I>(&self, v: V, opt: Opt, f: F) -> ValR2<V>
     where
         F: Fn(V) -> I,
@@ -62,13 +62,21 @@ impl<T> List<T> {
         cur
     }
 
-    #[cfg(test)]
-    fn iter(&self) -> impl Iterator<Item = &T> {
-        use alloc::boxed::Box;
-        match &*self.0 {
-            Node::Cons(x, xs) => Box::new(core::iter::once(x).chain(xs.iter())),
-            Node::Nil => Box::new(core::iter::empty()) as Box<dyn Iterator<Item = _>>,
-        }
+    /// Return true if both lists are the same, i.e. share the same memory.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        alloc::rc::Rc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Iterate over the list elements, starting from the most recently added.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        let mut cur = self;
+        core::iter::from_fn(move || match &*cur.0 {
+            Node::Cons(x, xs) => {
+                cur = xs;
+                Some(x)
+            }
+            Node::Nil => None,
+        })
     }
 }
 
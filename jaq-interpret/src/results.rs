@@ -3,7 +3,7 @@
 // TODO for v2.0: remove this from `results`
 pub use crate::box_iter::box_once;
 use crate::box_iter::BoxIter;
-use alloc::vec::Vec;
+use alloc::{boxed::Box, vec::Vec};
 
 /// A boxed iterator over `Result`s.
 pub type Results<'a, T, E> = BoxIter<'a, Result<T, E>>;
@@ -90,6 +90,24 @@ pub(crate) enum Fold<'a, U, E> {
     Output(Results<'a, U, E>),
 }
 
+/// Like `fold(true, ..)`, but yield the outputs of `extract` for every intermediate result.
+///
+/// Like `f`, `extract` receives the element of `xs` that yielded the intermediate result.
+pub(crate) fn fold_extract<'a, T: Clone + 'a, U: Clone + 'a, E: Clone + 'a>(
+    xs: impl Iterator<Item = Result<T, E>> + Clone + 'a,
+    init: U,
+    f: impl Fn(T, U) -> Results<'a, U, E> + 'a,
+    extract: impl Fn(T, U) -> Results<'a, U, E> + 'a,
+) -> impl Iterator<Item = Result<U, E>> + 'a {
+    // pair every intermediate result with the element that yielded it
+    let f = move |x: T, (_, y): (Option<T>, U)| -> Results<'a, _, E> {
+        Box::new(f(x.clone(), y).map(move |y| Ok((Some(x.clone()), y?))))
+    };
+    let ys = fold(true, xs, Fold::Input((None, init)), f);
+    // all intermediate results are outputs of `f`, so they are paired with some element
+    ys.flat_map(move |y| then(y, |(x, y)| extract(x.unwrap(), y)))
+}
+
 // if `inner` is true, output intermediate results
 pub(crate) fn fold<'a, T: Clone + 'a, U: Clone + 'a, E: Clone + 'a>(
     inner: bool,
@@ -97,6 +97,13 @@ pub trait ValT:
     /// This is used by `.[]`.
     fn values(self) -> Box<dyn Iterator<Item = ValR2<Self>>>;
 
+    /// Yield the children of a value together with their indices.
+    ///
+    /// This is used by `path(.[])`.
+    /// If `v.values()` yields `Ok(y)`, then `v.key_values()` should yield `Ok((k, y))`,
+    /// where `v.index(&k)` is `Ok(y)`.
+    fn key_values(self) -> Box<dyn Iterator<Item = Result<(Self, Self), Error<Self>>>>;
+
     /// Yield the child of a value at the given index.
     ///
     /// This is used by `.[k]`.
@@ -182,6 +189,21 @@ impl ValT for Val {
         }
     }
 
+    fn key_values(self) -> Box<dyn Iterator<Item = Result<(Self, Self), Error>>> {
+        match self {
+            Self::Arr(a) => {
+                let iter = rc_unwrap_or_clone(a).into_iter().enumerate();
+                Box::new(iter.map(|(i, v)| Ok((Self::Int(i as isize), v))))
+            }
+            Self::Obj(o) => Box::new(
+                rc_unwrap_or_clone(o)
+                    .into_iter()
+                    .map(|(k, v)| Ok((Self::Str(k), v))),
+            ),
+            _ => box_once(Err(Error::Type(self, Type::Iter))),
+        }
+    }
+
     fn index(self, index: &Self) -> ValR {
         match (self, index) {
             (Val::Arr(a), Val::Int(i)) => Ok(abs_index(*i, a.len())
@@ -194,26 +216,21 @@ impl ValT for Val {
     }
 
     fn range(self, range: Range<&Self>) -> ValR {
-        let (from, upto) = (range.start, range.end);
         match self {
             Val::Arr(a) => {
                 let len = a.len();
-                let from = from.as_ref().map(|i| i.as_int()).transpose();
-                let upto = upto.as_ref().map(|i| i.as_int()).transpose();
-                from.and_then(|from| Ok((from, upto?))).map(|(from, upto)| {
-                    let from = abs_bound(from, len, 0);
-                    let upto = abs_bound(upto, len, len);
+                range_ints(range).map(|range| {
+                    let from = abs_bound(range.start, len, 0);
+                    let upto = abs_bound(range.end, len, len);
                     let (skip, take) = skip_take(from, upto);
                     a.iter().skip(skip).take(take).cloned().collect()
                 })
             }
             Val::Str(s) => {
                 let len = s.chars().count();
-                let from = from.as_ref().map(|i| i.as_int()).transpose();
-                let upto = upto.as_ref().map(|i| i.as_int()).transpose();
-                from.and_then(|from| Ok((from, upto?))).map(|(from, upto)| {
-                    let from = abs_bound(from, len, 0);
-                    let upto = abs_bound(upto, len, len);
+                range_ints(range).map(|range| {
+                    let from = abs_bound(range.start, len, 0);
+                    let upto = abs_bound(range.end, len, len);
                     let (skip, take) = skip_take(from, upto);
                     Val::from(s.chars().skip(skip).take(take).collect::<String>())
                 })
@@ -295,15 +312,13 @@ impl ValT for Val {
     ) -> ValR {
         if let Val::Arr(ref mut a) = self {
             let a = Rc::make_mut(a);
-            let from = range.start.as_ref().map(|i| i.as_int()).transpose();
-            let upto = range.end.as_ref().map(|i| i.as_int()).transpose();
-            let (from, upto) = match from.and_then(|from| Ok((from, upto?))) {
-                Ok(from_upto) => from_upto,
+            let range = match range_ints(range) {
+                Ok(range) => range,
                 Err(e) => return opt.fail(self, |_| e),
             };
             let len = a.len();
-            let from = abs_bound(from, len, 0);
-            let upto = abs_bound(upto, len, len);
+            let from = abs_bound(range.start, len, 0);
+            let upto = abs_bound(range.end, len, len);
             let (skip, take) = skip_take(from, upto);
             let arr = Val::arr(a.iter().skip(skip).take(take).cloned().collect());
             let y = f(arr).map(|y| y?.into_arr()).next().transpose()?;
@@ -328,6 +343,12 @@ impl ValT for Val {
     }
 }
 
+/// Convert the bounds of a range to integers, where `null` stands for no bound.
+fn range_ints(range: Range<&Val>) -> Result<Range<isize>, Error> {
+    let int = |i: Option<&Val>| i.filter(|i| **i != Val::Null).map(Val::as_int);
+    Ok(int(range.start).transpose()?..int(range.end).transpose()?)
+}
+
 fn skip_take(from: usize, until: usize) -> (usize, usize) {
     (from, if until > from { until - from } else { 0 })
 }
@@ -465,10 +486,11 @@ impl Val {
 
     /// Return true if `value | .[key]` is defined.
     ///
+    /// Like in jq, negative array indices yield false.
     /// Fail on values that are neither arrays nor objects.
     pub fn has(&self, key: &Self) -> Result<bool, Error> {
         match (self, key) {
-            (Self::Arr(a), Self::Int(i)) if *i >= 0 => Ok((*i as usize) < a.len()),
+            (Self::Arr(a), Self::Int(i)) => Ok(usize::try_from(*i).map_or(false, |i| i < a.len())),
             (Self::Obj(o), Self::Str(s)) => Ok(o.contains_key(&**s)),
             _ => Err(Error::Index(self.clone(), key.clone())),
         }
@@ -540,9 +562,27 @@ impl Val {
     /// If the underlying lexer reads input fallibly (for example `IterLexer`),
     /// the error returned by this function might be misleading.
     /// In that case, always check whether the lexer contains an error.
+    ///
+    /// Like in jq, arrays and objects may be nested at most
+    /// [`Self::PARSE_DEPTH`] times; deeper values yield an error.
     #[cfg(feature = "hifijson")]
     pub fn parse(token: Token, lexer: &mut impl LexAlloc) -> Result<Self, hifijson::Error> {
+        Self::parse_bounded(Self::PARSE_DEPTH, token, lexer)
+    }
+
+    /// Maximal nesting depth of arrays and objects when parsing JSON.
+    ///
+    /// Parsing values nested this deeply takes up to about 4 MiB of stack in release builds.
+    pub const PARSE_DEPTH: usize = 10000;
+
+    #[cfg(feature = "hifijson")]
+    fn parse_bounded(
+        depth: usize,
+        token: Token,
+        lexer: &mut impl LexAlloc,
+    ) -> Result<Self, hifijson::Error> {
         use hifijson::{token, Error};
+        let inner = || depth.checked_sub(1).ok_or(Error::Depth);
         match token {
             Token::Null => Ok(Self::Null),
             Token::True => Ok(Self::Bool(true)),
@@ -560,21 +600,23 @@ impl Val {
             }
             Token::Quote => Ok(Self::str(lexer.str_string()?.to_string())),
             Token::LSquare => Ok(Self::arr({
+                let depth = inner()?;
                 let mut arr = Vec::new();
                 lexer.seq(Token::RSquare, |token, lexer| {
-                    arr.push(Self::parse(token, lexer)?);
+                    arr.push(Self::parse_bounded(depth, token, lexer)?);
                     Ok::<_, hifijson::Error>(())
                 })?;
                 arr
             })),
             Token::LCurly => Ok(Self::obj({
+                let depth = inner()?;
                 let mut obj: Map<_, _> = Default::default();
                 lexer.seq(Token::RCurly, |token, lexer| {
                     let key =
                         lexer.str_colon(token, |lexer| lexer.str_string().map_err(Error::Str))?;
 
                     let token = lexer.ws_token().ok_or(token::Expect::Value)?;
-                    let value = Self::parse(token, lexer)?;
+                    let value = Self::parse_bounded(depth, token, lexer)?;
                     obj.insert(Rc::new(key.to_string()), value);
                     Ok::<_, Error>(())
                 })?;
@@ -612,7 +654,8 @@ impl From<Val> for serde_json::Value {
             Val::Null => Null,
             Val::Bool(b) => Bool(b),
             Val::Int(i) => Number(i.into()),
-            Val::Float(f) => serde_json::Number::from_f64(f).map_or(Null, Number),
+            Val::Float(f) if f.is_nan() => Null,
+            Val::Float(f) => Number(serde_json::Number::from_str(&float_json(f)).unwrap()),
             Val::Num(n) => Number(serde_json::Number::from_str(&n).unwrap()),
             Val::Str(s) => String((*s).clone()),
             Val::Arr(a) => Array(a.iter().map(|x| x.clone().into()).collect()),
@@ -625,6 +668,49 @@ impl From<Val> for serde_json::Value {
     }
 }
 
+/// Format a finite floating-point number like jq when writing JSON.
+///
+/// This uses the shortest representation that yields the same number when parsed,
+/// omits the fractional part of integers (`1.0` becomes `1`), and
+/// uses exponential notation for numbers with large or small exponents
+/// (`1e100` becomes `1e+100` and `1e-7` becomes `1e-07`).
+/// Like in jq, infinities are written as the largest finite numbers
+/// with the same sign.
+fn float_json(x: f64) -> String {
+    let x = if x.is_infinite() {
+        f64::MAX.copysign(x)
+    } else {
+        x
+    };
+    // shortest representation in exponential notation, such as `-1.2345e-7`
+    let exp = alloc::format!("{x:e}");
+    let (mantissa, e) = exp.split_once('e').unwrap();
+    let e: isize = e.parse().unwrap();
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let len = digits.len() as isize;
+    // position of the decimal point relative to the first digit
+    let point = e + 1;
+    if point <= -4 || point > len + 15 {
+        let (first, rest) = digits.split_at(1);
+        let dot = if rest.is_empty() { "" } else { "." };
+        let e_sign = if e < 0 { '-' } else { '+' };
+        alloc::format!("{sign}{first}{dot}{rest}e{e_sign}{:02}", e.abs())
+    } else if point <= 0 {
+        let zeros = "0".repeat(-point as usize);
+        alloc::format!("{sign}0.{zeros}{digits}")
+    } else if point >= len {
+        let zeros = "0".repeat((point - len) as usize);
+        alloc::format!("{sign}{digits}{zeros}")
+    } else {
+        let (int, frac) = digits.split_at(point as usize);
+        alloc::format!("{sign}{int}.{frac}")
+    }
+}
+
 impl From<bool> for Val {
     fn from(b: bool) -> Self {
         Self::Bool(b)
@@ -782,7 +868,8 @@ impl core::ops::Neg for Val {
     fn neg(self) -> Self::Output {
         use Val::*;
         match self {
-            Int(x) => Ok(Int(-x)),
+            // the negation of the smallest integer is not an integer
+            Int(x) => Ok(x.checked_neg().map_or_else(|| Float(-(x as f64)), Int)),
             Float(x) => Ok(Float(-x)),
             Num(n) => -Self::from_dec_str(&n),
             x => Err(Error::Type(x, Type::Num)),
@@ -882,32 +969,44 @@ fn float_cmp(left: &f64, right: &f64) -> Ordering {
     }
 }
 
+/// Write a value as compact JSON.
+///
+/// The alternate form `{:#}` writes floats with fractional part, such as `1.0`.
+/// We use this in error messages to distinguish floats from integers.
 impl fmt::Display for Val {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::Null => write!(f, "null"),
             Self::Bool(b) => write!(f, "{b}"),
             Self::Int(i) => write!(f, "{i}"),
-            Self::Float(x) if x.is_finite() => write!(f, "{x:?}"),
-            Self::Float(_) => write!(f, "null"),
+            Self::Float(x) if x.is_finite() && f.alternate() => write!(f, "{x:?}"),
+            Self::Float(x) if x.is_nan() => write!(f, "null"),
+            Self::Float(x) => write!(f, "{}", float_json(*x)),
             Self::Num(n) => write!(f, "{n}"),
             Self::Str(s) => write!(f, "\"{s}\""),
             Self::Arr(a) => {
                 write!(f, "[")?;
                 let mut iter = a.iter();
                 if let Some(first) = iter.next() {
-                    write!(f, "{first}")?;
+                    fmt::Display::fmt(first, f)?;
                 };
-                iter.try_for_each(|x| write!(f, ",{x}"))?;
+                iter.try_for_each(|x| {
+                    write!(f, ",")?;
+                    fmt::Display::fmt(x, f)
+                })?;
                 write!(f, "]")
             }
             Self::Obj(o) => {
                 write!(f, "{{")?;
                 let mut iter = o.iter();
                 if let Some((k, v)) = iter.next() {
-                    write!(f, "\"{k}\":{v}")?;
+                    write!(f, "\"{k}\":")?;
+                    fmt::Display::fmt(v, f)?;
                 }
-                iter.try_for_each(|(k, v)| write!(f, ",\"{k}\":{v}"))?;
+                iter.try_for_each(|(k, v)| {
+                    write!(f, ",\"{k}\":")?;
+                    fmt::Display::fmt(v, f)
+                })?;
                 write!(f, "}}")
             }
         }
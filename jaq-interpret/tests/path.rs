@@ -1,7 +1,8 @@
 pub mod common;
 
 use common::{fail, give, gives};
-use jaq_interpret::Error;
+use jaq_interpret::error::{Error, Type};
+use jaq_interpret::Val;
 use serde_json::json;
 
 #[test]
@@ -53,6 +54,8 @@ fn iter_access() {
 fn range_access() {
     give(json!("Möwe"), ".[1:-1]", json!("öw"));
     give(json!("नमस्ते"), ".[1:5]", json!("मस्त"));
+    give(json!("héllo"), ".[1:3]", json!("él"));
+    give(json!("héllo"), ".[-3:9]", json!("llo"));
 
     give(json!([0, 1, 2]), ".[-4:4]", json!([0, 1, 2]));
     give(json!([0, 1, 2]), ".[0:3]", json!([0, 1, 2]));
@@ -60,6 +63,12 @@ fn range_access() {
     give(json!([0, 1, 2]), ".[:-1]", json!([0, 1]));
     give(json!([0, 1, 2]), ".[1:0]", json!([]));
     give(json!([0, 1, 2]), ".[4:5]", json!([]));
+    give(json!([0, 1, 2, 3]), ".[-2:]", json!([2, 3]));
+
+    // `null` bounds stand for the start and the end
+    give(json!([0, 1, 2]), "{}.a as $n | .[$n:2]", json!([0, 1]));
+    give(json!([0, 1, 2]), "{}.a as $n | .[1:$n]", json!([1, 2]));
+    give(json!("héllo"), "{}.a as $n | .[$n:-3]", json!("hé"));
 
     give(json!([0, 1, 2]), ".[0:2,3.14]?", json!([0, 1]));
 }
@@ -135,6 +144,7 @@ fn range_update() {
         ".[:2,3.0]? |= [.[] | .+1]",
         json!([1, 2, 2]),
     );
+    give(json!([0, 1, 2]), "{}.a as $n | .[$n:-1] |= []", json!([2]));
 }
 
 // Test what happens when update filter returns multiple values.
@@ -167,4 +177,48 @@ fn update_complex() {
     // works in jq when `a` is either null, a number, or a boolean --- it
     // does *not* work when `a` is a string, an array, or an object!
     fail(json!(0), "0 |= .+1", Error::PathExp);
+    fail(json!({"a": 1}), "(.a + 1 // .b) |= 2", Error::PathExp);
+}
+
+#[test]
+fn update_paths() {
+    give(
+        json!({"a": {"b": {"c": 1}}}),
+        ".a.b.c |= .+1",
+        json!({"a": {"b": {"c": 2}}}),
+    );
+
+    let xy = |x, y| json!({"x": x, "y": y});
+    give(
+        xy(json!(null), json!(1)),
+        "(.x // .y) |= .+1",
+        xy(json!(null), json!(2)),
+    );
+    give(
+        xy(json!(1), json!(1)),
+        "(.x // .y) |= .+1",
+        xy(json!(2), json!(1)),
+    );
+    give(
+        xy(json!(false), json!(1)),
+        "(.x, .y // .x) |= 0",
+        xy(json!(0), json!(0)),
+    );
+    give(
+        json!([[1]]),
+        "reduce (0, 0) as $i (.; .[$i]) |= .+1",
+        json!([[2]]),
+    );
+    // unlike jq, jaq does not create missing objects and arrays on update
+    let err = Error::Type(Val::Null, Type::Iter);
+    fail(json!({}), ".a.b.c |= .+1", err.clone());
+    fail(json!({}), ".a.b = 1", err.clone());
+    fail(json!({}), ".a[0] |= 1", err);
+
+    give(json!([1, 2]), "label $x | .[0] |= 0", json!([0, 2]));
+    give(
+        json!([1, 2]),
+        "(label $x | .[0], break $x, .[1]) |= 0",
+        json!([0, 2]),
+    );
 }
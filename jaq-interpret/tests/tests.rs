@@ -17,6 +17,12 @@ fn update_assign() {
 // idem for other arithmetic operations
 yields!(cartesian_arith, "[(1,2) * (3,4)]", [3, 4, 6, 8]);
 
+yields!(
+    big_int,
+    "[12345678901234567890 > 9223372036854775807, -12345678901234567890 < 0]",
+    [true, true]
+);
+
 #[test]
 fn add() {
     give(json!(1), ". + 2", json!(3));
@@ -36,6 +42,19 @@ fn add() {
     give(json!({"a": 1}), ". + {}", json!({"a": 1}));
 }
 
+// on conflicts, the right value wins, and the key keeps its position
+yields!(
+    add_obj_conflict,
+    r#"{"a": 1, "b": 2} + {"a": 9}"#,
+    json!({"a": 9, "b": 2})
+);
+// unlike `*`, `+` does not merge nested objects
+yields!(
+    add_obj_shallow,
+    r#"{"a": {"x": 1}} + {"a": {"y": 2}}"#,
+    json!({"a": {"y": 2}})
+);
+
 #[test]
 fn sub() {
     give(json!(1), ". - -2", json!(3));
@@ -43,7 +62,18 @@ fn sub() {
     give(json!(1.0), ". - 1", json!(0.0));
 }
 
+#[test]
+fn neg() {
+    give(json!(1), "-.", json!(-1));
+    give(json!(-1.5), "-.", json!(1.5));
+    give(json!(isize::MIN), "-.", json!(-(isize::MIN as f64)));
+}
+
 yields!(sub_arr, "[1, 2, 3] - [2, 3, 4]", json!([1]));
+// all occurrences are removed, and the remaining elements keep their order
+yields!(sub_arr_dup, "[1, 2, 3, 2, 1] - [1, 2]", json!([3]));
+yields!(sub_arr_order, "[3, 1, 4, 1, 5] - [1]", json!([3, 4, 5]));
+yields!(sub_arr_absent, "[1, 2, 1] - [3]", json!([1, 2, 1]));
 
 #[test]
 fn mul() {
@@ -63,6 +93,37 @@ fn mul() {
     );
 }
 
+// repeating a string less than once yields null
+yields!(
+    mul_str_count,
+    r#"["ab" * 0, "ab" * -1, "ab" * 3, 1 * "ab"]"#,
+    json!([null, null, "ababab", "ab"])
+);
+
+// objects are merged recursively
+yields!(
+    mul_obj_nested,
+    r#"{"a": {"x": 1}} * {"a": {"y": 2}}"#,
+    json!({"a": {"x": 1, "y": 2}})
+);
+yields!(
+    mul_obj_deep,
+    r#"{"a": {"b": {"x": 1}, "c": 3}} * {"a": {"b": {"y": 2}}, "d": 4}"#,
+    json!({"a": {"b": {"x": 1, "y": 2}, "c": 3}, "d": 4})
+);
+// non-objects on either side replace the value at the key
+yields!(mul_obj_scalar, r#"{"a": 1} * {"a": 2}"#, json!({"a": 2}));
+yields!(
+    mul_obj_replace,
+    r#"[{"a": {"x": 1}} * {"a": 2}, {"a": 1} * {"a": {"y": 2}}]"#,
+    json!([{"a": 2}, {"a": {"y": 2}}])
+);
+yields!(
+    mul_obj_num,
+    r#"try ({"a": 1} * 2) catch ."#,
+    "cannot calculate {\"a\":1} * 2"
+);
+
 yields!(div_str, r#""abcabcdab" / "ab""#, ["", "c", "cd", ""]);
 yields!(div_str_empty, r#""" / """#, json!([]));
 yields!(div_str_empty_str, r#""" / "ab""#, json!([]));
@@ -82,6 +143,8 @@ fn alt() {
     give(json!([null, 1, false, 2]), "[.[] // 0]", json!([1, 2]));
     give(json!([1, 2]), "[.[] // 0]", json!([1, 2]));
     give(json!([1, 2]), r#"[.[] // -"a"]"#, json!([1, 2]));
+    give(json!(0), "[.[0] // 1]", json!([1]));
+    give(json!(0), "[(.[0], 1) // 2]", json!([1]));
 }
 
 #[test]
@@ -227,6 +290,118 @@ yields!(
     true
 );
 
+yields!(label_break, "[label $x | 1, break $x, 2]", [1]);
+yields!(
+    label_break_outer,
+    "[label $x | 1, (label $y | 2, break $x), 3]",
+    [1, 2]
+);
+// `break` stops the innermost label with the given name
+yields!(
+    label_shadow,
+    "[label $x | 1, (label $x | 2, break $x, 3), 4]",
+    [1, 2, 4]
+);
+// labels are bound lexically, not dynamically
+yields!(
+    label_closure,
+    "def f(g): label $x | g, 3; [label $x | f(1, break $x), 2]",
+    [1]
+);
+yields!(
+    label_try,
+    "[label $x | 1, (try break $x catch 2), break $x?, 3]",
+    [1]
+);
+
+#[test]
+fn break_unbound() {
+    let (f, errs) = jaq_parse::parse("label $y | 1, break $x", jaq_parse::main());
+    assert!(errs.is_empty());
+    let mut ctx = jaq_interpret::ParseCtx::new(Vec::new());
+    ctx.compile(f.unwrap());
+    let errs = ctx
+        .errs
+        .iter()
+        .map(|(e, span)| (e.to_string(), span.clone()));
+    assert_eq!(
+        errs.collect::<Vec<_>>(),
+        [("undefined label".to_string(), 14..22)]
+    );
+}
+
+#[test]
+fn call_undefined() {
+    let errs = |f| {
+        let (f, errs) = jaq_parse::parse(f, jaq_parse::main());
+        assert!(errs.is_empty());
+        let mut ctx = jaq_interpret::ParseCtx::new(Vec::new());
+        ctx.compile(f.unwrap());
+        let errs = ctx
+            .errs
+            .iter()
+            .map(|(e, span)| (e.to_string(), span.clone()));
+        errs.collect::<Vec<_>>()
+    };
+    let err = |s: &str, span| [(s.to_string(), span)];
+    assert_eq!(
+        errs("def f: 1; xyz"),
+        err("undefined filter `xyz/0`", 10..13)
+    );
+    assert_eq!(
+        errs("def f(g): 1; f"),
+        err("undefined filter `f/0`, did you mean `f/1`?", 13..14)
+    );
+    assert_eq!(
+        errs("def fun(g): gg; 1"),
+        err("undefined filter `gg/0`, did you mean `g/0`?", 12..14)
+    );
+    assert_eq!(
+        errs("def length: 1; lenght"),
+        err(
+            "undefined filter `lenght/0`, did you mean `length/0`?",
+            15..21
+        )
+    );
+}
+
+#[test]
+fn recursion_limit() {
+    use jaq_interpret::{Ctx, Error, FilterT, ParseCtx, RcIter, Val, ValR};
+    fn run(f: &str) -> Vec<ValR> {
+        let (f, errs) = jaq_parse::parse(f, jaq_parse::main());
+        assert!(errs.is_empty());
+        let f = ParseCtx::new(Vec::new()).compile(f.unwrap());
+        let inputs = RcIter::new(core::iter::empty());
+        let ctx = Ctx::new([], &inputs).with_recursion_limit(10);
+        f.run((ctx, Val::Int(0))).collect()
+    }
+    let f = |n, main| format!("def f: if . < {n} then 1 + (.+1 | f) else 0 end; {main}");
+    assert_eq!(run(&f(9, "f")), [Ok(Val::Int(9))]);
+    assert_eq!(run(&f(10, "f")), [Err(Error::RecursionLimit)]);
+    let msg = Val::str("recursion limit exceeded".to_string());
+    assert_eq!(run(&f(10, "try f catch .")), [Ok(msg)]);
+    // tail calls do not count towards the limit
+    let tail = "def f: if . < 100 then .+1 | f else . end; f";
+    assert_eq!(run(tail), [Ok(Val::Int(100))]);
+    // tail calls with filter arguments do not count towards the limit either,
+    // even though evaluating `n` evaluates all previous arguments
+    let args = "def f(n): if n > 0 then f(n-1) else n end; f(50)";
+    assert_eq!(run(args), [Ok(Val::Int(0))]);
+}
+
+// tail calls are evaluated without growing the stack
+yields!(
+    tail_recursion,
+    "def f: if . < 3000000 then .+1 | f else . end; 0 | f",
+    3000000
+);
+yields!(
+    tail_recursion_args,
+    "def f($n): if $n > 0 then f($n - 1) else $n end; f(3000000)",
+    0
+);
+
 #[test]
 fn ord() {
     give(json!(null), ". < (0 != 0)", json!(true));
@@ -326,6 +501,16 @@ yields!(
     "[1, 2, 3] | [for .[] as $x (0; .+$x)]",
     [0, 1, 3, 6]
 );
+yields!(
+    foreach_extract,
+    "[1, 2, 3] | [foreach .[] as $x (0; .+$x; [$x, .])]",
+    json!([[1, 1], [2, 3], [3, 6]])
+);
+yields!(
+    foreach_extract_many,
+    "[foreach (1, 2) as $x (0; .+$x; {}[], ., -$x)]",
+    [1, -1, 3, -2]
+);
 
 // jq will give only [4, 3, 7, 12] here because
 // it keeps only the *last* output value as input value for the next iteration, whereas
@@ -340,3 +525,57 @@ yields!(
     "[for (3,4) as $x (1; .+$x, .*$x)]",
     [1, 4, 8, 16, 3, 7, 12]
 );
+
+yields!(reduce_sum, "reduce (1, 2, 3) as $x (0; .+$x)", 6);
+// every output of the update is folded over the remaining values:
+// `1 | (.+3 | .+4, .*4), (.*3 | .+4, .*4)`
+// jq yields only 12 here, because it folds only over the last output
+yields!(
+    reduce_many_outputs,
+    "[reduce (3,4) as $x (1; .+$x, .*$x)]",
+    [8, 16, 7, 12]
+);
+// jq yields null here
+yields!(
+    reduce_no_outputs,
+    "[reduce (3,4) as $x (1; {}[])]",
+    json!([])
+);
+yields!(
+    reduce_many_inits,
+    "[reduce (3,4) as $x (1, 2; .+$x)]",
+    [8, 9]
+);
+
+/// Compile a filter to its low-level definitions.
+fn compile(f: &str) -> jaq_interpret::Filter {
+    let mut ctx = jaq_interpret::ParseCtx::new(Vec::new());
+    let (f, errs) = jaq_parse::parse(f, jaq_parse::main());
+    assert!(errs.is_empty());
+    ctx.compile(f.unwrap())
+}
+
+#[test]
+fn fold_defs() {
+    let defs = |f| format!("{:?}", compile(f));
+    assert_eq!(defs("(1 + 2) * -3"), defs("-9"));
+    assert_eq!(defs(r#""a" + "b" + "c""#), defs(r#""abc""#));
+    assert_eq!(defs("4 // .a"), defs("4"));
+    assert_eq!(defs("[-(1 + 2), 3 * 4]"), defs("[-3, 12]"));
+
+    // overflow and floats are left to the interpreter
+    assert_ne!(defs("9223372036854775807 + 1"), defs("9223372036854775808"));
+    assert_ne!(defs("1.0 + 2"), defs("3.0"));
+    // the left-hand side of `//` may be `null` or `false`
+    assert_ne!(defs(". // 1"), defs("."));
+}
+
+yields!(fold_arith, "[(1 + 2) * -3, 2 - 5, 3 * 4]", [-9, -3, 12]);
+yields!(fold_str, r#""a" + "b" + "c""#, "abc");
+yields!(fold_alt, "[1 // 2, . // 3, (. | 4 | .)]", [1, 3, 4]);
+// floats are left to the interpreter
+yields!(fold_float, "[1.0 + 2, 1 + 2.5, -1.5]", [3.0, 3.5, -1.5]);
+// the left-hand side of `//` may be `null` or `false`
+yields!(fold_alt_falsy, "[{}.a // 1, (1 == 2) // 2]", [1, 2]);
+// binding a variable is not a plain pipe
+yields!(fold_pipe_var, "[1 as $x | 2, $x]", [2, 1]);
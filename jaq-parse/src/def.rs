@@ -1,7 +1,7 @@
 use super::{filter::filter, Delim, Token};
 use alloc::vec::Vec;
 use chumsky::prelude::*;
-use jaq_syn::{Arg, Call, Def, Main};
+use jaq_syn::{Arg, Call, Def, Import, Main, Module};
 
 /// A (potentially empty) parenthesised and `;`-separated sequence of arguments.
 fn args<T, P>(arg: P) -> impl Parser<Token, Vec<T>, Error = P::Error> + Clone
@@ -58,3 +58,47 @@ pub fn main() -> impl Parser<Token, Main, Error = Simple<Token>> + Clone {
         .then(filter())
         .map(|(defs, body)| Main { defs, body })
 }
+
+/// Parser for a module, consisting of metadata and dependencies, followed by a body.
+///
+/// Use this with [`defs`] to parse libraries and with [`main`] to parse programs.
+pub fn module<T, P>(body: P) -> impl Parser<Token, Module<T>, Error = Simple<Token>> + Clone
+where
+    P: Parser<Token, T, Error = Simple<Token>> + Clone,
+{
+    #[allow(clippy::result_large_err)]
+    let path = select! {
+        Token::Str(path) => path,
+    }
+    .delimited_by(just(Token::Quote), just(Token::Quote))
+    .map_with_span(|path, span| (path, span))
+    .labelled("module path");
+
+    let meta = just(Token::Module)
+        .ignore_then(filter())
+        .then_ignore(just(Token::Semicolon))
+        .labelled("module metadata");
+
+    #[allow(clippy::result_large_err)]
+    let alias = select! {
+        Token::Ident(alias) => alias,
+    };
+    let import = just(Token::Import)
+        .ignore_then(path.clone())
+        .then_ignore(just(Token::As))
+        .then(alias.map(Some));
+    let include = just(Token::Include)
+        .ignore_then(path)
+        .map(|path| (path, None));
+    let dep = import
+        .or(include)
+        .then(filter().or_not())
+        .then_ignore(just(Token::Semicolon))
+        .map(|((path, alias), meta)| Import { path, alias, meta })
+        .labelled("import");
+
+    meta.or_not()
+        .then(dep.repeated())
+        .then(body)
+        .map(|((meta, deps), body)| Module { meta, deps, body })
+}
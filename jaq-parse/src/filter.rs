@@ -32,17 +32,31 @@ where
 {
     let arg = || filter.clone().map(Box::new);
     let args = arg().then_ignore(just(Token::Semicolon)).then(arg());
+    let xs_x = arg().then_ignore(just(Token::As)).then(variable());
     let inner = select! {
         Token::Reduce => FoldType::Reduce,
         Token::For => FoldType::For,
-        Token::Foreach => FoldType::Foreach,
     };
-    inner
-        .then(arg())
-        .then_ignore(just(Token::As))
-        .then(variable())
-        .then(Delim::Paren.around(args))
-        .map(|(((inner, xs), x), (init, f))| (inner, Fold { xs, x, init, f }))
+    let fold = inner
+        .then(xs_x.clone())
+        .then(Delim::Paren.around(args.clone().map(|args| (args, None))));
+    // only `foreach` takes a third argument, namely the extractor
+    let extract = just(Token::Semicolon).ignore_then(arg()).or_not();
+    let foreach = just(Token::Foreach)
+        .to(FoldType::Foreach)
+        .then(xs_x)
+        .then(Delim::Paren.around(args.then(extract)));
+    fold.or(foreach)
+        .map(|((inner, (xs, x)), ((init, f), extract))| {
+            let fold = Fold {
+                xs,
+                x,
+                init,
+                f,
+                extract,
+            };
+            (inner, fold)
+        })
         .map_with_span(|(inner, fold), span| (Filter::Fold(inner, fold), span))
 }
 
@@ -56,6 +70,17 @@ where
         .map_with_span(|(try_, catch_), span| (Filter::TryCatch(try_, catch_), span))
 }
 
+fn label<P>(filter: P) -> impl Parser<Token, Spanned<Filter>, Error = P::Error> + Clone
+where
+    P: Parser<Token, Spanned<Filter>, Error = Simple<Token>> + Clone,
+{
+    just(Token::Label)
+        .ignore_then(variable())
+        .then_ignore(just(Token::Op("|".to_string())))
+        .then(filter.map(Box::new))
+        .map_with_span(|(x, f), span| (Filter::Label(x, f), span))
+}
+
 // 'Atoms' are filters that contain no ambiguity
 fn atom<P>(filter: P, no_comma: P) -> impl Parser<Token, Spanned<Filter>, Error = P::Error> + Clone
 where
@@ -74,6 +99,8 @@ where
 
     let recurse = just(Token::DotDot);
 
+    let break_ = just(Token::Break).ignore_then(variable());
+
     let array = Delim::Brack.around(filter.clone().or_not());
 
     let is_val = just(Token::Colon).ignore_then(no_comma);
@@ -97,6 +124,7 @@ where
         call.map_with_span(|call, span| (Filter::from(call), span)),
         variable().map_with_span(|v, span| (Filter::Var(v), span)),
         recurse.map_with_span(|_, span| (Filter::Recurse, span)),
+        break_.map_with_span(|x, span| (Filter::Break(x), span)),
     ))
 }
 
@@ -218,6 +246,7 @@ pub fn filter() -> impl Parser<Token, Spanned<Filter>, Error = Simple<Token>> +
         path.map_with_span(|(f, path), span| Filter::path(f, path, span)),
         fold(with_comma.clone()),
         if_then_else(with_comma.clone()),
+        label(with_comma.clone()),
     ))
     .boxed();
 
@@ -14,7 +14,7 @@ mod token;
 
 use jaq_syn as syn;
 
-pub use def::{defs, main};
+pub use def::{defs, main, module};
 use token::{Delim, Token};
 
 use alloc::{string::String, string::ToString, vec::Vec};
@@ -25,9 +25,9 @@ use syn::Spanned;
 pub type Error = Simple<String>;
 
 fn lex() -> impl Parser<char, Vec<Spanned<Token>>, Error = Simple<char>> {
-    recursive(token::tree)
-        .map_with_span(|tree, span| tree.tokens(span))
-        .repeated()
+    let tree = recursive(token::tree).map_with_span(|tree, span| tree.tokens(span));
+    token::space()
+        .ignore_then(tree.repeated())
         .flatten()
         .collect()
 }
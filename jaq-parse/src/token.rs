@@ -98,6 +98,11 @@ pub enum Token {
     Foreach,
     Try,
     Catch,
+    Label,
+    Break,
+    Module,
+    Import,
+    Include,
 }
 
 impl fmt::Display for Token {
@@ -128,6 +133,11 @@ impl fmt::Display for Token {
             Self::Foreach => "foreach".fmt(f),
             Self::Try => "try".fmt(f),
             Self::Catch => "catch".fmt(f),
+            Self::Label => "label".fmt(f),
+            Self::Break => "break".fmt(f),
+            Self::Module => "module".fmt(f),
+            Self::Import => "import".fmt(f),
+            Self::Include => "include".fmt(f),
         }
     }
 }
@@ -177,7 +187,8 @@ fn char_() -> impl Parser<char, char, Error = Simple<char>> {
 pub fn tree(
     tree: impl Parser<char, Tree, Error = Simple<char>> + Clone,
 ) -> impl Parser<char, Tree, Error = Simple<char>> {
-    let trees = || tree.clone().map_with_span(|t, span| (t, span)).repeated();
+    // skip leading whitespace, such that the span of each tree starts with the tree
+    let trees = || space().ignore_then(tree.clone().map_with_span(|t, span| (t, span)).repeated());
     let paren = trees().delimited_by(just('('), just(')'));
     let brack = trees().delimited_by(just('['), just(']'));
     let brace = trees().delimited_by(just('{'), just('}'));
@@ -193,8 +204,6 @@ pub fn tree(
         .delimited_by(just('"'), just('"'))
         .labelled("string");
 
-    let comment = just("#").then(take_until(just('\n'))).padded();
-
     let strategy = |open, close, others| {
         nested_delimiters(open, close, others, |_span| Tree::Token(Token::Dot))
     };
@@ -209,8 +218,13 @@ pub fn tree(
     .recover_with(strategy('(', ')', [('[', ']'), ('{', '}')]))
     .recover_with(strategy('[', ']', [('{', '}'), ('(', ')')]))
     .recover_with(strategy('{', '}', [('(', ')'), ('[', ']')]))
-    .padded_by(comment.repeated())
-    .padded()
+    .then_ignore(space())
+}
+
+/// Whitespace and comments.
+pub fn space() -> impl Parser<char, (), Error = Simple<char>> + Clone {
+    let comment = just("#").then(take_until(just('\n'))).padded();
+    comment.repeated().padded().ignored()
 }
 
 fn token() -> impl Parser<char, Token, Error = Simple<char>> {
@@ -219,8 +233,13 @@ fn token() -> impl Parser<char, Token, Error = Simple<char>> {
 
     let var = just('$').ignore_then(text::ident());
 
-    // A parser for identifiers and keywords
+    // A parser for identifiers and keywords,
+    // where identifiers may be qualified by a module name, such as `foo::f`
+    let qualified = just(':')
+        .chain(just(':'))
+        .chain::<char, _, _>(text::ident());
     let ident = just('@').or_not().chain::<char, _, _>(text::ident());
+    let ident = ident.chain::<char, _, _>(qualified.repeated().flatten());
     let ident = ident.collect().map(|ident: String| match ident.as_str() {
         "def" => Token::Def,
         "if" => Token::If,
@@ -236,6 +255,11 @@ fn token() -> impl Parser<char, Token, Error = Simple<char>> {
         "foreach" => Token::Foreach,
         "try" => Token::Try,
         "catch" => Token::Catch,
+        "label" => Token::Label,
+        "break" => Token::Break,
+        "module" => Token::Module,
+        "import" => Token::Import,
+        "include" => Token::Include,
         _ => Token::Ident(ident),
     });
 
@@ -2,9 +2,20 @@
 
 pub mod common;
 
-use common::{give, gives};
+use common::{fail, give, gives};
 use serde_json::json;
 
+#[test]
+fn abs() {
+    give(json!(-3), "abs", json!(3));
+    give(json!(3), "abs", json!(3));
+    give(json!(-3.5), "abs", json!(3.5));
+    give(json!(0.5), "abs", json!(0.5));
+    give(json!(isize::MIN), "abs", json!(-(isize::MIN as f64)));
+    let err = jaq_interpret::Error::Val(json!("\"a\" has no absolute value").into());
+    fail(json!("a"), "abs", err);
+}
+
 #[test]
 fn add() {
     give(json!({"a": 1, "b": 2}), "add", json!(3));
@@ -18,11 +29,48 @@ fn all() {
 
     let f = "def positive(f): all(f; . > 0); positive(.[])";
     give(json!([1, 2]), f, json!(true));
+
+    give(json!([1, 2]), "all(. > 1)", json!(false));
+    give(json!([]), "all(. > 1)", json!(true));
+    // stop at the first false
+    give(json!(null), "all(range(0; 1/0; 1); . < 5)", json!(false));
+    give(json!(null), "all(1, 0, error; . == 1)", json!(false));
+    give(json!(null), "all(repeat(false); .)", json!(false));
 }
 
 #[test]
 fn any() {
     give(json!({"a": false, "b": true}), "any", json!(true));
+
+    give(json!([1, 2]), "any(. > 1)", json!(true));
+    give(json!([]), "any(. > 1)", json!(false));
+    // stop at the first true
+    give(json!(null), "any(range(0; 1/0; 1); . == 5)", json!(true));
+    give(json!(null), "any(0, 1, error; . == 1)", json!(true));
+    give(json!(null), "any(repeat(true); .)", json!(true));
+}
+
+yields!(ascii, "[65, 97, 48] | map(ascii) | add", "Aa0");
+yields!(
+    ascii_invalid,
+    r#"[128, -1, "a" | try ascii catch -1]"#,
+    [-1, -1, -1]
+);
+
+#[test]
+fn combinations() {
+    let f = "[combinations]";
+    give(
+        json!([[1, 2], [3, 4]]),
+        f,
+        json!([[1, 3], [1, 4], [2, 3], [2, 4]]),
+    );
+    give(json!([]), f, json!([[]]));
+    give(json!([[1, 2], []]), f, json!([]));
+
+    let f = "[combinations(2)]";
+    give(json!([0, 1]), f, json!([[0, 0], [0, 1], [1, 0], [1, 1]]));
+    give(json!([0, 1]), "[combinations(0)]", json!([[]]));
 }
 
 #[test]
@@ -35,11 +83,7 @@ fn date() {
         json!(86400.123456789),
     );
     give(json!(86400), "todate", json!("1970-01-02T00:00:00Z"));
-    give(
-        json!(86400.123456789),
-        "todate",
-        json!("1970-01-02T00:00:00.123456789Z"),
-    );
+    give(json!(86400.5), "todate", json!("1970-01-02T00:00:00Z"));
 }
 
 #[test]
@@ -47,14 +91,49 @@ fn date_roundtrip() {
     let epoch = 946684800;
     give(json!(epoch), "todate|fromdate", json!(epoch));
     let epoch_ns = 946684800.123456;
-    give(json!(epoch_ns), "todate|fromdate", json!(epoch_ns));
+    give(json!(epoch_ns), "todate|fromdate", json!(epoch));
 
     let iso = "2000-01-01T00:00:00Z";
     give(json!(iso), "fromdate|todate", json!(iso));
     let iso_ns = "2000-01-01T00:00:00.123456000Z";
-    give(json!(iso_ns), "fromdate|todate", json!(iso_ns));
+    give(json!(iso_ns), "fromdate|todate", json!(iso));
 }
 
+yields!(
+    date_alias,
+    r#""2015-03-05T23:51:47Z" | [fromdate, fromdateiso8601, (fromdate | todate, todateiso8601)]"#,
+    json!([
+        1425599507,
+        1425599507,
+        "2015-03-05T23:51:47Z",
+        "2015-03-05T23:51:47Z"
+    ])
+);
+yields!(
+    date_add_sub,
+    r#"1700000000 | [date, dateadd("days"; 1), datesub("weeks"; 1), dateadd("seconds"; 0.5)]"#,
+    json!(["2023-11-14T22:13:20Z", 1700086400, 1699395200, 1700000000.5])
+);
+yields!(
+    date_add_unit,
+    r#"0 | try dateadd("months"; 1) catch ."#,
+    "unsupported date unit: months"
+);
+// the difference between two dates in seconds
+yields!(
+    date_diff,
+    r#"("2015-03-06T00:00:00Z" | fromdate) - ("2015-03-05T12:00:00Z" | fromdate)"#,
+    43200
+);
+yields!(
+    date_offset,
+    r#""2015-03-05T23:51:47+01:00" | [try fromdate catch -1, try fromdateiso8601 catch -1]"#,
+    [-1, -1]
+);
+
+// messages are only logged, the input is passed through
+yields!(debug_msgs, "[1, 2 | debug(\"x\", .)]", [1, 2]);
+
 yields!(
     drem_nan,
     "[drem(nan, 1; nan, 1)] == [nan, nan, nan, 0.0]",
@@ -81,8 +160,92 @@ fn entries() {
     give(arr, "to_entries", entries);
 
     give(json!([]), "from_entries", json!({}));
+
+    // jq accepts several names for keys and values
+    let entries = json!([{"k": "a", "v": 1}, {"name": "b", "value": 2}, {"Name": "c"}]);
+    give(entries, "from_entries", json!({"a": 1, "b": 2, "c": null}));
+    // non-string keys are converted to strings, and later keys take precedence
+    let entries = json!([{"key": 1, "value": 1}, {"key": null}, {"key": 1, "value": 2}]);
+    give(entries, "from_entries", json!({"1": 2, "null": null}));
+    let entries = json!([{"key": false, "value": false, "v": 1}]);
+    give(entries, "from_entries", json!({"false": false}));
 }
 
+#[test]
+fn error_catch() {
+    // errors without `catch` are swallowed
+    give(json!(null), r#"[try error("a")]"#, json!([]));
+    // errors in `catch` are propagated
+    let err = jaq_interpret::Error::Val(json!("b").into());
+    fail(json!(null), r#"try error("a") catch error("b")"#, err);
+    give(json!(null), r#"try error("a") catch ."#, json!("a"));
+}
+
+#[test]
+fn error_null() {
+    // a `null` error is still an error
+    give(
+        json!(null),
+        r#"try (null | error) catch "caught""#,
+        json!("caught"),
+    );
+    give(json!(null), "[try error(null) catch .]", json!([null]));
+    let err = jaq_interpret::Error::Val(json!(null).into());
+    fail(json!(null), "error(null)", err);
+}
+
+// the caught value is the error payload, not its string representation
+yields!(error_obj, "try error({code: 404}) catch .code", 404);
+
+// all matching elements are deleted, not only every second one
+yields!(
+    del_select,
+    "[1, 2, 3, 2] | del(.[] | select(. == 2))",
+    [1, 3]
+);
+// all paths refer to the original value, so indices remain valid
+yields!(del_indices, "[1, 2, 3] | del(.[1, 2])", [1]);
+// paths that refer to the same value delete it only once
+yields!(del_duplicate, "[1, 2, 3] | del(.[0, 0])", [2, 3]);
+yields!(
+    del_duplicate_nested,
+    r#"{"a": [1, 2, 3]} | del(.a[0], .a[0])"#,
+    json!({"a": [2, 3]})
+);
+yields!(
+    del_duplicate_slice,
+    "[1, 2, 3, 4] | del(.[0:2], .[0:2])",
+    [3, 4]
+);
+yields!(del_negative, "[1, 2, 3] | del(.[0], .[-3])", [2, 3]);
+yields!(
+    del_slice_index,
+    "[1, 2, 3, 4] | del(.[1:3][0], .[-1])",
+    [1, 3]
+);
+yields!(
+    del_multi,
+    r#"{"a": 1, "b": [1, 2]} | del(.a, .b[0])"#,
+    json!({"b": [2]})
+);
+yields!(del_root, "[1] | del(.)", json!(null));
+yields!(
+    delpaths_select,
+    "[1, 2, 3, 2] | delpaths([paths(. == 2)])",
+    [1, 3]
+);
+
+// `first` must not evaluate its argument beyond the first output
+yields!(first_repeat, "first(repeat(1))", 1);
+yields!(first_comma, "first(1, error)", 1);
+yields!(first_recurse, "0 | first(recurse(.+1) | select(. > 2))", 3);
+// the tail of the linked list is never visited, which would yield an error
+yields!(
+    first_recurse_list,
+    "reduce range(1000) as $i (0; {v: $i, next: .}) | first(recurse(.next)) | .v",
+    999
+);
+
 #[test]
 fn flatten() {
     let a0 = || json!([1, [{"a": 2}, [3]]]);
@@ -104,6 +267,51 @@ yields!(flatten_obj, "{a: 1} | flatten", json!([{"a": 1}]));
 // jq gives an error here
 yields!(flatten_num, "0 | flatten", [0]);
 
+yields!(
+    group_by_stream,
+    "GROUP_BY({a: 1, b: 1}, {a: 2, b: 2}, {a: 1, b: 3}; .a) | map(map(.b))",
+    json!([[1, 3], [2]])
+);
+
+#[test]
+fn index_rindex() {
+    let f = "[index(1), rindex(1)]";
+    give(json!([0, 1, 2, 1, 2]), f, json!([1, 3]));
+    give(json!([0, 2]), f, json!([null, null]));
+
+    let f = "[index([1, 2]), rindex([1, 2])]";
+    give(json!([0, 1, 2, 1, 2]), f, json!([1, 3]));
+
+    let f = r#"[index(","), rindex(",")]"#;
+    give(json!("a,b,c"), f, json!([1, 3]));
+    give(
+        json!("äbc"),
+        r#"[index("b"), rindex("x")]"#,
+        json!([1, null]),
+    );
+}
+
+#[test]
+fn index_sql() {
+    let x = json!([{"id": 1, "n": "a"}, {"id": "1", "n": "b"}, {"id": 2, "n": "c"}]);
+    // keys are converted to strings, and later rows take precedence
+    let y = json!({"1": {"id": "1", "n": "b"}, "2": {"id": 2, "n": "c"}});
+    give(x.clone(), "INDEX(.id)", y.clone());
+    give(x, "INDEX(.[]; .id)", y);
+    give(json!([]), "INDEX(.id)", json!({}));
+}
+
+#[test]
+fn in_sql() {
+    give(json!(2), "IN(1, 2, 3)", json!(true));
+    give(json!(4), "IN(1, 2, 3)", json!(false));
+    give(json!(null), "IN(1, 2; 3, 2)", json!(true));
+    give(json!(null), "IN(1, 2; 3, 4)", json!(false));
+    // stop at the first match
+    give(json!(2), "IN(1, 2, error)", json!(true));
+    give(json!(2), "IN(repeat(2))", json!(true));
+}
+
 #[test]
 fn inside() {
     give(
@@ -124,6 +332,23 @@ fn inside() {
     give(json!({"foo": 12, "bar": [{"barp": 15}]}), f, json!(false));
 }
 
+yields!(isempty_repeat, "isempty(repeat(1))", false);
+
+yields!(infinite_gt, "infinite > 1e308", true);
+// non-finite values are preserved through arithmetic
+yields!(
+    infinite_arith,
+    "[infinite - 1, -infinite * 2] == [infinite, -infinite]",
+    true
+);
+
+yields!(
+    isinfinite_true,
+    "all(infinite, -infinite; isinfinite)",
+    true
+);
+yields!(isinfinite_false, "any(0, 1e308, nan; isinfinite)", false);
+
 yields!(isfinite_true, "all((0, 1, nan); isfinite)", true);
 yields!(
     isfinite_false,
@@ -131,6 +356,14 @@ yields!(
     false
 );
 
+yields!(isnan_true, "nan | isnan", true);
+yields!(
+    isnan_arith,
+    "[nan + 1, nan * 0, infinite - infinite] | all(isnan)",
+    true
+);
+yields!(isnan_false, "any(0, infinite, null; isnan)", false);
+
 yields!(isnormal_true, "1 | isnormal", true);
 yields!(
     isnormal_false,
@@ -148,6 +381,24 @@ yields!(
 yields!(join_nums, r#"[2, 3, 4, 5] | join(1)"#, 17);
 
 yields!(map, "[1, 2] | map(.+1)", [2, 3]);
+// unlike `map`, `map_values` keeps only the first output of `f` for every element,
+// and deletes elements for which `f` yields no output
+yields!(
+    map_values_obj,
+    "{a: 1, b: 2} | map_values(., .+1)",
+    json!({"a": 1, "b": 2})
+);
+yields!(map_values_arr, "[1, 2] | map_values(., .+1)", [1, 2]);
+yields!(
+    map_values_empty,
+    "{a: 1, b: 2} | map_values(empty)",
+    json!({})
+);
+yields!(
+    map_values_select,
+    "[1, 2, 3, 2] | map_values(select(. != 2))",
+    [1, 3]
+);
 
 yields!(
     keys,
@@ -203,6 +454,13 @@ fn nth() {
     give(json!(10), &format!("nth(.; {})", fib), json!(55));
 }
 
+yields!(nth_inf, "nth(2; repeat(1, 2))", 1);
+yields!(
+    nth_neg,
+    "try nth(-1; 1, 2) catch .",
+    "Out of bounds negative array index"
+);
+
 yields!(paths_num, "1 | [paths]", json!([]));
 yields!(paths_null, "null | [paths]", json!([]));
 yields!(paths_arr, "[1, 2] | [paths]", [[0], [1]]);
@@ -211,6 +469,35 @@ yields!(
     "{a: [1, [2]], b: {c: 3}} | [paths]",
     json!([["a"], ["a", 0], ["a", 1], ["a", 1, 0], ["b"], ["b", "c"]])
 );
+yields!(
+    paths_numbers,
+    r#"{a: 1, b: [2, "x", {c: 3}], d: "y", e: null} | [paths(numbers)]"#,
+    json!([["a"], ["b", 0], ["b", 2, "c"]])
+);
+
+yields!(
+    pick_obj,
+    r#"{a: 1, b: 2, c: {d: 3, e: 4}} | pick(.a, .c.d)"#,
+    json!({"a": 1, "c": {"d": 3}})
+);
+// paths that do not exist are created with null values
+yields!(
+    pick_missing,
+    r#"{a: 1, b: 2} | pick(.c.d, .a, .e[1])"#,
+    json!({"c": {"d": null}, "a": 1, "e": [null, null]})
+);
+yields!(
+    pick_first,
+    "{a: 1, b: 2} | pick(first(.b, .a))",
+    json!({"b": 2})
+);
+yields!(pick_null, "null | pick(.a)", json!({"a": null}));
+// arrays are padded with nulls up to the picked index
+yields!(
+    pick_arr,
+    r#"{x: [10, 20, 30]} | pick(.x[2])"#,
+    json!({"x": [null, null, 30]})
+);
 
 yields!(range_many, "[range(-1, 1; 0, 2)]", json!([-1, -1, 0, 1, 1]));
 
@@ -232,6 +519,8 @@ yields!(
 yields!(limit_inf_suml, "[limit(3; recurse(.+1) + 0)]", [0, 1, 2]);
 yields!(limit_inf_sumr, "[limit(3; 0 + recurse(.+1))]", [0, 1, 2]);
 
+yields!(limit_repeat, "[limit(3; repeat(1))]", [1, 1, 1]);
+yields!(limit_recurse, "[limit(1; 0 | recurse(.+1))]", [0]);
 yields!(limit_inf_path, "[limit(2; [1] | .[repeat(0)])]", [1, 1]);
 
 #[test]
@@ -241,10 +530,21 @@ fn recurse() {
 
     let y = [json!(1), json!(2), json!(3)];
     gives(json!(1), "recurse(.+1; . < 4)", y);
+    // like in jq, outputs of `f` that do not satisfy `cond` are not yielded,
+    // but the input is yielded even if it does not satisfy `cond`
+    give(json!(1), "[recurse(.+1; . < 4)] | length", json!(3));
+    give(json!(9), "[recurse(.+1; . < 4)]", json!([9]));
 
     let y = [json!(2), json!(4), json!(16)];
     gives(json!(2), "recurse(. * .; . < 20)", y);
 
+    // `recurse/1` and `recurse/2` are path expressions if their arguments are
+    let x = json!({"a": {"a": {"b": 1}}});
+    let y = json!([[], ["a"], ["a", "a"]]);
+    give(x.clone(), "[path(recurse(.a; . != null))]", y);
+    let y = json!([[], ["a"], ["a", "a"], ["a", "a", "b"]]);
+    give(x, "[path(recurse(.[]?))]", y);
+
     let x = json!([[[0], 1], 2, [3, [4]]]);
 
     let y = json!([[[1], 2], 3, [4, [5]]]);
@@ -299,6 +599,18 @@ yields!(
 fn repeat() {
     let y = json!([0, 1, 0, 1]);
     give(json!([0, 1]), "[limit(4; repeat(.[]))]", y);
+
+    // `repeat` must stop in the middle of an output sequence of `f`
+    give(
+        json!(null),
+        "[limit(5; repeat(1, 2, 3))]",
+        json!([1, 2, 3, 1, 2]),
+    );
+    give(
+        json!(null),
+        "[limit(2; repeat(1, 2, error))]",
+        json!([1, 2]),
+    );
 }
 
 // the implementation of scalb in jq (or the libm.a library) doesn't
@@ -365,6 +677,31 @@ yields!(
     [-1.929, -1.1, -1.0, -1.0, 0.0, 1.31072, 1.0, 1.0, 1.1, 1.929]
 );
 
+yields!(toarray_num, "1 | toarray", [1]);
+yields!(toarray_arr, "[1] | toarray", [1]);
+yields!(toarray_null, "null | toarray", json!([null]));
+
+#[test]
+fn tostream() {
+    let x = json!({"a": [1, {"b": 2}], "c": [], "d": {}});
+    let ys = [
+        json!([["a", 0], 1]),
+        json!([["a", 1, "b"], 2]),
+        json!([["a", 1, "b"]]),
+        json!([["a", 1]]),
+        json!([["c"], []]),
+        json!([["d"], {}]),
+        json!([["d"]]),
+    ];
+    gives(x.clone(), "tostream", ys);
+    give(json!(1), "tostream", json!([[], 1]));
+    give(x.clone(), "fromstream(tostream)", x);
+
+    // `fromstream` yields a value whenever a top-level value is complete
+    let f = "[fromstream((1, [2, [3]], {}) | tostream)]";
+    give(json!(null), f, json!([1, [2, [3]], {}]));
+}
+
 #[test]
 fn transpose() {
     let y = json!([[1, 2], [3, null]]);
@@ -374,6 +711,13 @@ fn transpose() {
     give(json!([[1, 3], [2, 4]]), "transpose", y);
 }
 
+#[test]
+fn truncate_stream() {
+    let f = r#"[1 | truncate_stream({"a": [1, {"b": 2}]} | tostream)]"#;
+    let y = json!([[[0], 1], [[1, "b"], 2], [[1, "b"]], [[1]]]);
+    give(json!(null), f, y);
+}
+
 #[test]
 fn typ() {
     give(json!({"a": 1, "b": 2}), "type", json!("object"));
@@ -385,6 +729,18 @@ fn typ() {
     give(json!(null), "type", json!("null"));
 }
 
+// values are compared structurally, and the first of equal values is kept
+yields!(
+    unique_eq,
+    r#"[1, 1.0, {"a": 1}, {"a": 1}] | unique | map(tojson)"#,
+    ["1", "{\"a\":1}"]
+);
+yields!(
+    unique_obj_order,
+    r#"[1.0, 1, {"b": [2], "a": 1}, {"a": 1, "b": [2]}, 0] | unique | map(tojson)"#,
+    ["0", "1.0", "{\"b\":[2],\"a\":1}"]
+);
+
 #[test]
 fn walk() {
     give(
@@ -418,6 +774,10 @@ fn while_until() {
         json!([[1, 2, 3], [2, 3], [3]]),
     );
 
+    // `while` is lazy, so it can be used to generate infinite streams
+    give(json!(1), "[limit(3; while(true; . + 1))]", json!([1, 2, 3]));
+
+    give(json!(1), "until(. > 100; . * 2)", json!(128));
     give(json!(50), "until(. > 100; . * 2)", json!(200));
     give(
         json!([1, 2, 3]),
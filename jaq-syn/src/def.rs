@@ -130,3 +130,36 @@ pub struct Main<F = Filter> {
     /// Body of the filter, e.g. `[.[] | f`.
     pub body: Spanned<F>,
 }
+
+/// Module, consisting of metadata and dependencies, followed by a body.
+///
+/// For example, the module
+/// `module {name: "m"}; import "foo" as foo; include "bar"; def f: foo::g;`
+/// has the metadata `{name: "m"}`, the two dependencies `foo` and `bar`, and
+/// the body `def f: foo::g;`.
+/// The body is a sequence of definitions for libraries and
+/// a [`Main`] for programs.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct Module<B> {
+    /// metadata given by `module {...};`
+    pub meta: Option<Spanned<Filter>>,
+    /// dependencies given by `import` and `include`
+    pub deps: Vec<Import>,
+    /// definitions, potentially followed by a filter
+    pub body: B,
+}
+
+/// Dependency of a module, such as `import "foo" as foo {search: "."};` or `include "bar";`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct Import {
+    /// path of the imported module, such as `"foo"`
+    pub path: Spanned<String>,
+    /// name under which the definitions of the module are accessible, such as `foo` in `foo::f`
+    ///
+    /// If this is `None`, then the definitions are included directly.
+    pub alias: Option<String>,
+    /// metadata given after the path, such as `{search: "."}`
+    pub meta: Option<Spanned<Filter>>,
+}
@@ -85,6 +85,21 @@ pub struct Fold<F> {
     pub init: F,
     /// Updater
     pub f: F,
+    /// Extractor, which is only used by `foreach` and defaults to `.`
+    pub extract: Option<F>,
+}
+
+impl<F> Fold<F> {
+    /// Apply a function to the contained filters.
+    pub fn map<G>(self, mut f: impl FnMut(F) -> G) -> Fold<G> {
+        Fold {
+            xs: f(self.xs),
+            x: self.x,
+            init: f(self.init),
+            f: f(self.f),
+            extract: self.extract.map(f),
+        }
+    }
 }
 
 /// Type of folding filter.
@@ -127,6 +142,7 @@ pub enum Filter<C = String, V = String, Num = String> {
         Option<Box<Spanned<Self>>>,
     ),
     /// `reduce` and `foreach`, e.g. `reduce .[] as $x (0; .+$x)`
+    /// or `foreach .[] as $x (0; .+$x; [$x, .])`
     ///
     /// The first field indicates whether to yield intermediate results
     /// (`false` for `reduce` and `true` for `foreach`).
@@ -135,6 +151,10 @@ pub enum Filter<C = String, V = String, Num = String> {
     TryCatch(Box<Spanned<Self>>, Option<Box<Spanned<Self>>>),
     /// Error suppression, e.g. `keys?`
     Try(Box<Spanned<Self>>),
+    /// Label, e.g. `label $x | 1, break $x`
+    Label(String, Box<Spanned<Self>>),
+    /// Break out of the enclosing label with the given name, e.g. `break $x`
+    Break(V),
     /// Negation
     Neg(Box<Spanned<Self>>),
     /// Recursion (`..`)
@@ -12,7 +12,7 @@ pub mod path;
 pub mod string;
 pub mod test;
 
-pub use def::{Arg, Call, Def, Main};
+pub use def::{Arg, Call, Def, Import, Main, Module};
 pub use ops::{MathOp, OrdOp};
 use path::Path;
 pub use string::Str;
@@ -0,0 +1,96 @@
+//! Colors of JSON output, as given by the `JQ_COLORS` environment variable.
+//!
+//! Like in jq, `JQ_COLORS` contains colon-separated colors for
+//! `null`, `false`, `true`, numbers, strings, arrays, objects, and object keys,
+//! such as `1;30:0;39:0;39:0;39:0;32:1;39:1;39:34;1`.
+//! Every color consists of semicolon-separated SGR parameters of ANSI escape sequences.
+//! If fewer colors are given, then the remaining ones keep their default.
+
+use colored_json::{Color, Style, Styler};
+
+thread_local! {
+    /// Styles of JSON output, read from `JQ_COLORS` once when they are first used
+    static STYLER: Styler = from_env();
+}
+
+/// Return the styles of JSON output.
+pub fn styler() -> Styler {
+    STYLER.with(|s| *s)
+}
+
+fn from_env() -> Styler {
+    let mut styler = Styler::default();
+    if let Ok(spec) = std::env::var("JQ_COLORS") {
+        if set(&mut styler, &spec).is_none() {
+            eprintln!("Warning: failed to set $JQ_COLORS");
+            return Styler::default();
+        }
+    }
+    styler
+}
+
+/// Set the styles given by a color specification.
+///
+/// jaq uses the same style for `false` and `true`, namely the last one given.
+fn set(styler: &mut Styler, spec: &str) -> Option<()> {
+    let styles = spec.split(':').map(style).collect::<Option<Vec<_>>>()?;
+    if styles.len() > 8 {
+        return None;
+    }
+    for (i, s) in styles.into_iter().enumerate() {
+        match i {
+            0 => styler.nil_value = s,
+            1 | 2 => styler.bool_value = s,
+            3 => (styler.integer_value, styler.float_value) = (s, s),
+            4 => styler.string_value = s,
+            5 => styler.array_brackets = s,
+            6 => (styler.object_brackets, styler.object_colon) = (s, s),
+            _ => styler.key = s,
+        }
+    }
+    Some(())
+}
+
+/// Convert SGR parameters, such as `1;31`, to a style.
+fn style(sgr: &str) -> Option<Style> {
+    let mut codes = sgr.split(';').filter(|c| !c.is_empty()).map(|c| c.parse());
+    let mut style = Style::default();
+    while let Some(code) = codes.next() {
+        style = match code.ok()? {
+            0 => Style::default(),
+            1 => style.bold(),
+            2 => style.dimmed(),
+            3 => style.italic(),
+            4 => style.underline(),
+            5 => style.blink(),
+            7 => style.invert(),
+            8 => style.hidden(),
+            9 => style.strikethrough(),
+            c @ 30..=37 => style.fg(basic(c - 30)),
+            38 => style.fg(extended(&mut codes)?),
+            39 => style.fg(Color::Default),
+            c @ 40..=47 => style.bg(basic(c - 40)),
+            48 => style.bg(extended(&mut codes)?),
+            49 => style.bg(Color::Default),
+            c @ 90..=97 => style.fg(Color::Fixed(c - 90 + 8)),
+            c @ 100..=107 => style.bg(Color::Fixed(c - 100 + 8)),
+            _ => return None,
+        }
+    }
+    Some(style)
+}
+
+fn basic(n: u8) -> Color {
+    use Color::*;
+    [Black, Red, Green, Yellow, Blue, Magenta, Cyan, White][usize::from(n)]
+}
+
+/// Parse the remainder of a 256-color (`5;n`) or RGB color (`2;r;g;b`).
+fn extended<E>(codes: &mut impl Iterator<Item = Result<u8, E>>) -> Option<Color> {
+    let mut next = || codes.next()?.ok();
+    match next()? {
+        5 => Some(Color::Fixed(next()?)),
+        2 => Some(Color::RGB(next()?, next()?, next()?)),
+        _ => None,
+    }
+}
@@ -0,0 +1,83 @@
+//! Parsing of the elements of top-level arrays one by one.
+//!
+//! When a filter starts with `.[]`, such as `.[] | .a`, and does not read inputs,
+//! then running it on an array `[x1, ..., xn]` yields the same outputs as
+//! running it on the singleton arrays `[x1]`, ..., `[xn]` one after the other.
+//! This allows running such filters on large arrays without holding them in memory entirely.
+
+use hifijson::token::{Expect, Token};
+use hifijson::{Error, LexAlloc};
+use jaq_interpret::Val;
+use jaq_syn::filter::{BinaryOp, Filter};
+use jaq_syn::path::Part;
+
+/// Return whether a filter starts with `.[]`, such as `.[]`, `.[]?.a`, or `.[] | f`.
+pub fn starts_with_iter(f: &Filter) -> bool {
+    match f {
+        Filter::Path(f, path) => {
+            matches!(f.0, Filter::Id) && matches!(path.first(), Some((Part::Range(None, None), _)))
+        }
+        Filter::Binary(l, BinaryOp::Pipe(None), _) => starts_with_iter(&l.0),
+        _ => false,
+    }
+}
+
+/// Position of the parser between two elements.
+#[derive(Clone, Default)]
+pub struct Elements {
+    /// whether we are inside a top-level array
+    inside: bool,
+}
+
+impl Elements {
+    /// Parse the next element, returning `None` if there is no more input.
+    ///
+    /// Every element `x` of a top-level array is yielded as `[x]`, and
+    /// top-level values that are not arrays are yielded as they are.
+    pub fn next<L: LexAlloc>(&mut self, lexer: &mut L) -> Option<Result<Val, Error>> {
+        loop {
+            let elem = if self.inside {
+                self.rest(lexer)
+            } else {
+                let token = lexer.ws_token()?;
+                self.first(token, lexer)
+            };
+            // continue after empty arrays
+            if let Some(elem) = elem.transpose() {
+                return Some(elem);
+            }
+        }
+    }
+
+    /// Parse the beginning of a top-level value and yield its first element, if any.
+    fn first<L: LexAlloc>(&mut self, token: Token, lexer: &mut L) -> Result<Option<Val>, Error> {
+        if token != Token::LSquare {
+            return Val::parse(token, lexer).map(Some);
+        }
+        let token = lexer.ws_token().ok_or(Expect::ValueOrEnd)?;
+        if token == Token::RSquare {
+            return Ok(None);
+        }
+        self.inside = true;
+        element(token, lexer).map(Some)
+    }
+
+    /// Parse the rest of a top-level array after an element of it.
+    fn rest<L: LexAlloc>(&mut self, lexer: &mut L) -> Result<Option<Val>, Error> {
+        match lexer.ws_token().ok_or(Expect::CommaOrEnd)? {
+            Token::Comma => (),
+            Token::RSquare => {
+                self.inside = false;
+                return Ok(None);
+            }
+            _ => Err(Expect::CommaOrEnd)?,
+        }
+        let token = lexer.ws_token().ok_or(Expect::Value)?;
+        element(token, lexer).map(Some)
+    }
+}
+
+/// Parse an element of an array and wrap it in a singleton array.
+fn element<L: LexAlloc>(token: Token, lexer: &mut L) -> Result<Val, Error> {
+    Ok(Val::arr(Vec::from([Val::parse(token, lexer)?])))
+}
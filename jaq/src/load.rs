@@ -0,0 +1,416 @@
+//! Loading of modules from the file system.
+//!
+//! A program may refer to modules via `include "path";` and `import "path" as name;`.
+//! Included definitions are inserted into the including module as if they were defined there,
+//! whereas imported definitions can only be called by their qualified names, such as `name::f`.
+
+use crate::ParseError;
+use chumsky::error::Simple;
+use jaq_interpret::{Ctx, Error, FilterT, ParseCtx, RcIter, Val, ValR, ValT};
+use jaq_syn::filter::{Filter, KeyVal};
+use jaq_syn::{Def, Import, Main, Module, Spanned, Str};
+use std::path::{Path, PathBuf};
+
+/// Names of imported modules together with their IDs.
+type Aliases = Vec<(String, usize)>;
+
+/// Loader of modules that remembers which modules were already imported.
+pub struct Loader<'a> {
+    /// directories where modules are searched
+    search: Vec<PathBuf>,
+    /// paths of imported modules, where the index of a path serves as ID of the module
+    imported: Vec<PathBuf>,
+    /// names and arities of the definitions of imported modules, indexed by module ID
+    exports: Vec<Vec<(String, usize)>>,
+    /// paths of modules that are currently being loaded, to detect import cycles
+    loading: Vec<PathBuf>,
+    /// definitions of all imported modules, where dependencies precede their dependents
+    pub defs: Vec<Def>,
+    /// function that returns errors (such as undefined filters) in the given definitions
+    check: &'a dyn Fn(Vec<Def>) -> Vec<Spanned<String>>,
+}
+
+impl<'a> Loader<'a> {
+    pub fn new(search: Vec<PathBuf>, check: &'a dyn Fn(Vec<Def>) -> Vec<Spanned<String>>) -> Self {
+        Self {
+            search,
+            imported: Vec::new(),
+            exports: Vec::new(),
+            loading: Vec::new(),
+            defs: Vec::new(),
+            check,
+        }
+    }
+
+    /// Resolve the dependencies of a program, where `src` is the source of the program and
+    /// `path` is the file containing the program, if any.
+    /// Dependencies are searched relative to the directory of `path`,
+    /// or relative to the current directory if there is no such file.
+    ///
+    /// The returned program may call definitions in `self.defs`.
+    pub fn main(
+        &mut self,
+        path: Option<&Path>,
+        src: &str,
+        m: Module<Main>,
+    ) -> Result<Main, Vec<ParseError>> {
+        let dir = path.map_or(Path::new("."), |p| p.parent().unwrap_or(Path::new(".")));
+        let name = path.map_or("<top-level>".to_string(), |p| p.display().to_string());
+        let (includes, aliases) = self.deps(dir, src, m.deps)?;
+        let defs = includes.into_iter().chain(m.body.defs).collect();
+        let main = Main {
+            defs,
+            body: m.body.body,
+        };
+        let rename = Rename::new(&aliases, &self.exports);
+        Ok(rename.with_source(&name, src).main(main))
+    }
+
+    /// Load the dependencies of a module and
+    /// return the included definitions as well as the names and IDs of imported modules.
+    fn deps(
+        &mut self,
+        dir: &Path,
+        src: &str,
+        deps: Vec<Import>,
+    ) -> Result<(Vec<Def>, Aliases), Vec<ParseError>> {
+        let (mut includes, mut aliases) = (Vec::new(), Vec::new());
+        for Import { path, alias, meta } in deps {
+            let err = |msg: String| {
+                let error = Simple::custom(path.1.clone(), msg);
+                let filter = src.to_string();
+                vec![ParseError { error, filter }]
+            };
+            let meta = match meta {
+                Some(meta) => Some(constant(meta).ok_or_else(|| err(META_ERR.to_string()))?),
+                None => None,
+            };
+            let file = find(dir, meta.as_ref(), &self.search, &path.0);
+            let file = file.ok_or_else(|| err(format!("module not found: {}", path.0)))?;
+            if self.loading.contains(&file) {
+                return Err(err(format!("import cycle: {}", file.display())));
+            }
+
+            match alias {
+                None => includes.extend(self.lib(&file).map_err(|e| e.unwrap_or_else(err))?),
+                Some(alias) => {
+                    let id = match self.imported.iter().position(|p| *p == file) {
+                        Some(id) => id,
+                        None => {
+                            let defs = self.lib(&file).map_err(|e| e.unwrap_or_else(err))?;
+                            let id = self.imported.len();
+                            let sigs = defs.iter().map(sig).collect();
+                            self.defs.extend(Rename::own(id).defs(defs));
+                            self.imported.push(file);
+                            self.exports.push(sigs);
+                            id
+                        }
+                    };
+                    aliases.push((alias, id));
+                }
+            }
+        }
+        Ok((includes, aliases))
+    }
+
+    /// Load a library and return its definitions.
+    ///
+    /// If the library cannot be read, return an error message,
+    /// which the caller reports at the location where the library is imported.
+    fn lib(&mut self, path: &Path) -> Result<Vec<Def>, Result<Vec<ParseError>, String>> {
+        let src = std::fs::read_to_string(path);
+        let src = src.map_err(|e| Err(format!("cannot read {}: {e}", path.display())))?;
+        let (module, errs) = jaq_parse::parse(&src, jaq_parse::module(jaq_parse::defs()));
+        let module = match module {
+            Some(module) if errs.is_empty() => module,
+            _ => {
+                let errs = errs.into_iter().map(|error| ParseError {
+                    error,
+                    filter: src.clone(),
+                });
+                return Err(Ok(errs.collect()));
+            }
+        };
+        if let Some(meta) = module.meta {
+            if constant(meta.clone()).is_none() {
+                let error = Simple::custom(meta.1, META_ERR.to_string());
+                return Err(Ok(vec![ParseError { error, filter: src }]));
+            }
+        }
+
+        self.loading.push(path.to_path_buf());
+        let deps = self.deps(path.parent().unwrap(), &src, module.deps);
+        self.loading.pop();
+
+        let (includes, aliases) = deps.map_err(Ok)?;
+        let defs = includes.into_iter().chain(module.body).collect();
+        let name = path.display().to_string();
+        let rename = Rename::new(&aliases, &self.exports);
+        let defs = rename.with_source(&name, &src).defs(defs);
+
+        let errs = (self.check)(self.defs.iter().cloned().chain(defs.clone()).collect());
+        if !errs.is_empty() {
+            let errs = errs.into_iter().map(|(msg, span)| ParseError {
+                error: Simple::custom(span, msg),
+                filter: src.clone(),
+            });
+            return Err(Ok(errs.collect()));
+        }
+        Ok(defs)
+    }
+}
+
+const META_ERR: &str = "module metadata must be a constant object";
+
+/// Find the file of a module with the given relative path, such as `foo/bar`.
+///
+/// For every directory in the search path, we look for
+/// `foo/bar.jq` and `foo/bar/bar.jq`, in that order.
+/// The search path consists of the directories given by the `search` key in the metadata,
+/// which are relative to `dir`, followed by `search`.
+fn find(dir: &Path, meta: Option<&Val>, search: &[PathBuf], rel: &str) -> Option<PathBuf> {
+    let local = match meta.map(|m| m.clone().index(&Val::str("search".to_string()))) {
+        Some(Ok(Val::Str(s))) => Vec::from([dir.join(&*s)]),
+        Some(Ok(Val::Arr(a))) => a
+            .iter()
+            .filter_map(|s| s.as_str().ok())
+            .map(|s| dir.join(&**s))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let name = Path::new(rel).file_name()?.to_str()?;
+    let dirs = local.into_iter().chain(search.iter().cloned());
+    let files = dirs.flat_map(|d| {
+        [
+            d.join(format!("{rel}.jq")),
+            d.join(rel).join(format!("{name}.jq")),
+        ]
+    });
+    files
+        .filter(|f| f.is_file())
+        .find_map(|f| f.canonicalize().ok())
+}
+
+/// Evaluate constant metadata, such as `{search: "."}`, returning it if it is an object.
+fn constant(meta: Spanned<Filter>) -> Option<Val> {
+    let mut defs = ParseCtx::new(Vec::new());
+    let body = meta;
+    let f = defs.compile(Main {
+        defs: Vec::new(),
+        body,
+    });
+    if !defs.errs.is_empty() {
+        return None;
+    }
+    let inputs = RcIter::new(core::iter::empty());
+    let mut out = f.run((Ctx::new([], &inputs), Val::Null));
+    out.next()?.ok().filter(|v| matches!(v, Val::Obj(_)))
+}
+
+/// Return the metadata of the module with the given relative path, as `modulemeta` does.
+///
+/// This consists of the metadata given by `module {...};`, extended with
+/// `deps` (the dependencies of the module) and `defs` (the names of its definitions).
+pub fn modulemeta(search: &[PathBuf], rel: Val) -> ValR {
+    let rel = rel.as_str()?;
+    let file = find(Path::new("."), None, search, rel);
+    let file = file.ok_or_else(|| Error::str(format!("module not found: {rel}")))?;
+    let src = std::fs::read_to_string(&file);
+    let src = src.map_err(|e| Error::str(format!("cannot read {}: {e}", file.display())))?;
+    let (module, errs) = jaq_parse::parse(&src, jaq_parse::module(jaq_parse::defs()));
+    let module = module.filter(|_| errs.is_empty());
+    let module = module.ok_or_else(|| Error::str(format!("cannot parse module {rel}")))?;
+
+    let obj = |kvs: Vec<(&str, Val)>| {
+        let kvs = kvs.into_iter().map(|(k, v)| (k.to_string().into(), v));
+        Val::obj(kvs.collect())
+    };
+    let constant = |meta: Option<Spanned<Filter>>| match meta {
+        Some(meta) => constant(meta).ok_or_else(|| Error::str(META_ERR)),
+        None => Ok(obj(Vec::new())),
+    };
+
+    let deps = module.deps.into_iter().map(|dep| {
+        let mut kvs = Vec::new();
+        if let Some(alias) = dep.alias {
+            kvs.push(("as", Val::str(alias)));
+        }
+        kvs.push(("is_data", Val::Bool(false)));
+        kvs.push(("relpath", Val::str(dep.path.0)));
+        constant(dep.meta)? + obj(kvs)
+    });
+    let deps = deps.collect::<Result<_, _>>()?;
+    let defs = module.body.iter();
+    let defs = defs.map(|def| Val::str(format!("{}/{}", def.lhs.name, def.lhs.args.len())));
+    let extra = obj(Vec::from([
+        ("deps", Val::arr(deps)),
+        ("defs", Val::arr(defs.collect())),
+    ]));
+    constant(module.meta)? + extra
+}
+
+/// Renaming of calls to filters defined in modules.
+///
+/// This also replaces `$__loc__` by an object containing
+/// the name of the file and the line where `$__loc__` occurs.
+///
+/// When a module gets the ID `id`, its definitions are renamed from `f` to `{id}:f`.
+/// Because users cannot write such names,
+/// these definitions can be called only via the name of their module, such as `foo::f`,
+/// which we rename to `{id}:f` if `foo` refers to the module with ID `id`.
+/// If that module does not define `f`, we keep the name `foo::f`,
+/// so that the error about the undefined filter shows the name written by the user.
+#[derive(Default)]
+struct Rename<'a> {
+    /// names and IDs of imported modules
+    aliases: &'a [(String, usize)],
+    /// names and arities of the definitions of imported modules, indexed by module ID
+    exports: &'a [Vec<(String, usize)>],
+    /// ID of the module whose definitions we rename, together with the definitions renamed so far
+    own: Option<(usize, Vec<(String, usize)>)>,
+    /// names and arities of locally bound filters, which shadow all other filters
+    local: Vec<(String, usize)>,
+    /// name and contents of the file that contains the definitions we rename
+    source: Option<(&'a str, &'a str)>,
+}
+
+/// Return the value of `$__loc__`, such as `{"file": "<top-level>", "line": 1}`,
+/// when it occurs at the given span of the file `name` with contents `src`.
+fn loc(name: &str, src: &str, span: &jaq_syn::Span) -> Filter {
+    let line = src.chars().take(span.start).filter(|c| *c == '\n').count() + 1;
+    let kv = |k: &str, v| KeyVal::Str(Str::from(k.to_string()), Some((v, span.clone())));
+    let file = Filter::from(Str::from(name.to_string()));
+    Filter::Object(Vec::from([
+        kv("file", file),
+        kv("line", Filter::Num(line.to_string())),
+    ]))
+}
+
+fn mangle(id: usize, name: &str) -> String {
+    format!("{id}:{name}")
+}
+
+fn sig(def: &Def) -> (String, usize) {
+    (def.lhs.name.clone(), def.lhs.args.len())
+}
+
+impl<'a> Rename<'a> {
+    fn new(aliases: &'a [(String, usize)], exports: &'a [Vec<(String, usize)>]) -> Self {
+        Self {
+            aliases,
+            exports,
+            ..Self::default()
+        }
+    }
+
+    fn own(id: usize) -> Self {
+        Self {
+            own: Some((id, Vec::new())),
+            ..Self::default()
+        }
+    }
+
+    fn with_source(self, name: &'a str, src: &'a str) -> Self {
+        let source = Some((name, src));
+        Self { source, ..self }
+    }
+
+    /// Rename top-level definitions of a module.
+    fn defs(mut self, defs: Vec<Def>) -> Vec<Def> {
+        let mut renamed = Vec::new();
+        for mut def in defs {
+            if let Some((id, own)) = &mut self.own {
+                own.push(sig(&def));
+                def.lhs.name = mangle(*id, &def.lhs.name);
+            }
+            renamed.push(self.def(def));
+        }
+        renamed
+    }
+
+    fn def(&mut self, def: Def) -> Def {
+        let len = self.local.len();
+        let args = def.lhs.args.iter().filter_map(|arg| arg.get_filter());
+        self.local.extend(args.map(|arg| (arg.to_string(), 0)));
+        let rhs = self.main(def.rhs);
+        self.local.truncate(len);
+        Def { lhs: def.lhs, rhs }
+    }
+
+    fn main(&mut self, main: Main) -> Main {
+        let len = self.local.len();
+        let defs = main.defs.into_iter().map(|def| {
+            self.local.push(sig(&def));
+            self.def(def)
+        });
+        let defs = defs.collect();
+        let body = self.filter(main.body);
+        self.local.truncate(len);
+        Main { defs, body }
+    }
+
+    /// Return the new name of a call to a filter, if it should be renamed.
+    fn call(&self, name: &str, arity: usize) -> Option<String> {
+        let is = |(name_, arity_): &(String, usize)| name_ == name && *arity_ == arity;
+        if self.local.iter().any(is) {
+            return None;
+        }
+        match &self.own {
+            Some((id, own)) if own.iter().any(is) => return Some(mangle(*id, name)),
+            _ => (),
+        }
+        let (alias, name) = name.split_once("::")?;
+        let (_, id) = self.aliases.iter().rev().find(|(a, _)| a == alias)?;
+        let mut exported = self.exports[*id].iter();
+        exported
+            .any(|(name_, arity_)| name_ == name && *arity_ == arity)
+            .then(|| mangle(*id, name))
+    }
+
+    fn filter(&mut self, f: Spanned<Filter>) -> Spanned<Filter> {
+        let get = |ctx: &mut Self, f| Box::new(ctx.filter(f));
+        let result = match f.0 {
+            Filter::Call(name, args) => {
+                let args: Vec<_> = args.into_iter().map(|arg| self.filter(arg)).collect();
+                let name = self.call(&name, args.len()).unwrap_or(name);
+                Filter::Call(name, args)
+            }
+            Filter::Str(s) => Filter::Str(Box::new((*s).map(|f| self.filter(f)))),
+            Filter::Array(a) => Filter::Array(a.map(|a| get(self, *a))),
+            Filter::Object(o) => {
+                Filter::Object(o.into_iter().map(|kv| kv.map(|f| self.filter(f))).collect())
+            }
+            Filter::Path(f, path) => {
+                let f = get(self, *f);
+                let path = path
+                    .into_iter()
+                    .map(|(p, opt)| (p.map(|p| self.filter(p)), opt));
+                Filter::Path(f, path.collect())
+            }
+            Filter::Ite(if_thens, else_) => {
+                let if_thens = if_thens
+                    .into_iter()
+                    .map(|(i, t)| (self.filter(i), self.filter(t)));
+                Filter::Ite(if_thens.collect(), else_.map(|else_| get(self, *else_)))
+            }
+            Filter::Fold(typ, fold) => Filter::Fold(typ, fold.map(|f| get(self, *f))),
+            Filter::TryCatch(try_, catch_) => {
+                Filter::TryCatch(get(self, *try_), catch_.map(|c| get(self, *c)))
+            }
+            Filter::Try(f) => Filter::Try(get(self, *f)),
+            Filter::Label(x, f) => Filter::Label(x, get(self, *f)),
+            Filter::Neg(f) => Filter::Neg(get(self, *f)),
+            Filter::Binary(l, op, r) => Filter::Binary(get(self, *l), op, get(self, *r)),
+            Filter::Var(x) if x == "__loc__" && self.source.is_some() => {
+                let (name, src) = self.source.unwrap();
+                loc(name, src, &f.1)
+            }
+            f @ (Filter::Var(_)
+            | Filter::Num(_)
+            | Filter::Id
+            | Filter::Recurse
+            | Filter::Break(_)) => f,
+        };
+        (result, f.1)
+    }
+}
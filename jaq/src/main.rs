@@ -1,7 +1,15 @@
+mod color;
+mod elements;
+mod load;
+#[cfg(feature = "simd-json")]
+mod simd;
+mod stream;
+
 use clap::{Parser, ValueEnum};
-use jaq_interpret::{Ctx, Filter, FilterT, ParseCtx, RcIter, Val};
+use jaq_interpret::results::box_once;
+use jaq_interpret::{Ctx, Filter, FilterT, Native, ParseCtx, RcIter, Val};
 use std::io::{self, BufRead, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{ExitCode, Termination};
 
 #[cfg(feature = "mimalloc")]
@@ -51,38 +59,110 @@ struct Cli {
     #[arg(short = 'R', long)]
     raw_input: bool,
 
+    /// Parse the input as a stream of `[path, leaf]` and `[path]` events
+    ///
+    /// Every input value is converted to the events that `tostream` would yield,
+    /// without holding the whole value in memory.
+    /// The original values can be recovered with `fromstream(inputs)`.
+    #[arg(long)]
+    stream: bool,
+
+    /// Read and write JSON text sequences (RFC 7464)
+    ///
+    /// When reading, values may be preceded by the record separator (ASCII 0x1E),
+    /// and malformed values between two separators are skipped with a warning.
+    /// When writing, every value is preceded by the record separator and
+    /// followed by a newline.
+    #[arg(long)]
+    seq: bool,
+
     /// Print JSON compactly, omitting whitespace
     #[arg(short, long)]
     compact_output: bool,
 
+    /// Print the keys of objects in sorted order
+    ///
+    /// This sorts the keys of all objects, including nested ones.
+    #[arg(short = 'S', long)]
+    sort_keys: bool,
+
     /// Use n spaces for indentation
+    ///
+    /// If n is larger than 7 (the maximum that jq accepts), then 7 spaces are used.
     #[arg(long, value_name = "n", default_value_t = 2)]
     indent: usize,
 
+    /// Escape all non-ASCII characters in JSON output
+    ///
+    /// For example, `"é"` is printed as `"\u00e9"`.
+    /// Strings printed with `--raw-output` are not escaped.
+    #[arg(short, long)]
+    ascii_output: bool,
+
     /// Use tabs for indentation rather than spaces
     #[arg(long)]
     tab: bool,
 
     /// Do not print a newline after each value
     ///
-    /// Unlike jq, this does not enable `--raw-output`.
+    /// This implies `--raw-output`.
     #[arg(short, long)]
     join_output: bool,
 
     /// Color output
+    ///
+    /// By default, output is colored if it is written to a terminal and
+    /// the environment variable `NO_COLOR` is not set to a non-empty value.
+    /// Like in jq, colors can be customised via the environment variable `JQ_COLORS`.
     #[arg(long, value_name = "WHEN", default_value = "auto")]
     color: Color,
 
+    /// Always color output, like `--color always`
+    #[arg(short = 'C', long, conflicts_with = "monochrome_output")]
+    color_output: bool,
+
+    /// Never color output, like `--color never`
+    #[arg(short = 'M', long)]
+    monochrome_output: bool,
+
     /// Read filter from a file
     ///
     /// In this case, all arguments are interpreted as input files.
+    /// If the first argument is a valid filter, such as `.` or `.a`,
+    /// then it is most likely not an input file, so jaq fails
+    /// instead of ignoring either the filter or the file.
+    /// Names of input files, such as `in.json`, are usually not valid filters,
+    /// because they refer to undefined filters (here `in`).
     #[arg(short, long, value_name = "FILE")]
     from_file: Option<PathBuf>,
 
+    /// Search for modules in directory
+    ///
+    /// This option may be given several times.
+    /// Directories given by this option are searched before
+    /// those given by the environment variable `JAQ_LIBRARY_PATH`.
+    /// If neither is given, then
+    /// `~/.jq`, `$ORIGIN/../lib/jq`, and `$ORIGIN/../lib` are searched,
+    /// where `$ORIGIN` is the directory containing the jaq executable.
+    #[arg(short = 'L', long, value_name = "DIR")]
+    library_path: Vec<PathBuf>,
+
+    /// Fail when filter calls are nested more deeply than n
+    ///
+    /// This prevents deeply recursive filters from overflowing the stack.
+    /// Tail calls, such as in `def f: if . < 100 then .+1 | f else . end`,
+    /// do not count towards this limit.
+    #[arg(long, value_name = "n", default_value_t = jaq_interpret::RECURSION_LIMIT)]
+    recursion_limit: usize,
+
     /// Set variable `$<a>` to string `<v>`
     #[arg(long, value_names = &["a", "v"])]
     arg: Vec<String>,
 
+    /// Set variable `$<a>` to JSON value `<v>`
+    #[arg(long, value_names = &["a", "v"])]
+    argjson: Vec<String>,
+
     /// Set variable `$<a>` to string containing the contents of file `f`
     #[arg(long, value_names = &["a", "f"])]
     rawfile: Vec<String>,
@@ -91,6 +171,18 @@ struct Cli {
     #[arg(long, value_names = &["a", "f"])]
     slurpfile: Vec<String>,
 
+    /// Interpret remaining arguments as strings for `$ARGS.positional`
+    ///
+    /// In this case, no input files are read.
+    #[arg(long = "args", conflicts_with = "json_args")]
+    str_args: bool,
+
+    /// Interpret remaining arguments as JSON values for `$ARGS.positional`
+    ///
+    /// In this case, no input files are read.
+    #[arg(long = "jsonargs")]
+    json_args: bool,
+
     /// Run tests from a file
     #[arg(long, value_name = "FILE")]
     run_tests: Option<PathBuf>,
@@ -107,21 +199,55 @@ enum Color {
 }
 
 impl Cli {
+    /// Return true if the remaining arguments are values for `$ARGS.positional`.
+    fn positional(&self) -> bool {
+        self.str_args || self.json_args
+    }
+
+    /// Return the arguments following the filter.
+    fn rest(&self) -> &[String] {
+        match self.from_file {
+            Some(_) => &self.args,
+            None => self.args.get(1..).unwrap_or_default(),
+        }
+    }
+
     fn color_mode(&self) -> colored_json::ColorMode {
         use colored_json::{ColorMode, Output};
+        let no_color = std::env::var_os("NO_COLOR").map_or(false, |c| !c.is_empty());
         match self.color {
+            _ if self.color_output => ColorMode::On,
+            _ if self.monochrome_output => ColorMode::Off,
             Color::Always => ColorMode::On,
-            Color::Auto if self.in_place => ColorMode::Off,
+            Color::Auto if self.in_place || no_color => ColorMode::Off,
             Color::Auto => ColorMode::Auto(Output::StdOut),
             Color::Never => ColorMode::Off,
         }
     }
 }
 
+/// Stack size of the thread that runs jaq.
+///
+/// This is large enough to evaluate filters that nest calls up to
+/// the default recursion limit as well as to parse JSON values nested up to
+/// the maximal parsing depth.
+const STACK_SIZE: usize = 256 << 20;
+
 fn main() -> ExitCode {
-    match real_main() {
+    let main = || match real_main() {
         Ok(exit) => exit,
         Err(e) => e.report(),
+    };
+    // if we cannot spawn a thread with a large stack,
+    // then we run jaq on the main thread, which has a smaller stack
+    match std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(main)
+    {
+        Ok(thread) => thread
+            .join()
+            .unwrap_or_else(|e| std::panic::resume_unwind(e)),
+        Err(_) => main(),
     }
 }
 
@@ -130,59 +256,108 @@ fn real_main() -> Result<ExitCode, Error> {
 
     use env_logger::Env;
     env_logger::Builder::from_env(Env::default().filter_or("LOG", "debug"))
-        // omit name of module that emitted log message
-        .format_target(false)
+        // print only the message, such as `["DEBUG:",1]` for `1 | debug`
+        .format(|buf, record| match record.level() {
+            // `stderr` does not terminate its output with a newline
+            log::Level::Info => write!(buf, "{}", record.args()),
+            _ => writeln!(buf, "{}", record.args()),
+        })
         .init();
 
     if let Some(test_file) = &cli.run_tests {
         return Ok(run_tests(std::fs::File::open(test_file)?));
     }
 
-    let (vars, ctx) = binds(&cli)?.into_iter().unzip();
+    let (vars, ctx): (Vec<_>, _) = binds(&cli)?.into_iter().unzip();
+    SEARCH.with(|s| *s.borrow_mut() = search_path(&cli));
 
     let mut args = cli.args.iter();
-    let filter = match &cli.from_file {
-        Some(file) => parse(&std::fs::read_to_string(file)?, vars)?,
+    let (filter, elements) = match &cli.from_file {
+        Some(file) => {
+            // like in jq, an argument is an input file if a file with its name exists
+            let is_filter = |arg: &&String| {
+                !std::path::Path::new(arg).exists() && parse(arg, vars.clone(), None).is_ok()
+            };
+            let first = cli.args.first().filter(|_| !cli.positional());
+            if let Some(arg) = first.filter(is_filter) {
+                use clap::{error::ErrorKind, CommandFactory};
+                let msg = format!("cannot use filter `{arg}` together with `--from-file`");
+                Cli::command()
+                    .error(ErrorKind::ArgumentConflict, msg)
+                    .exit()
+            }
+            parse(&std::fs::read_to_string(file)?, vars, Some(file))?
+        }
         None => {
             if let Some(filter) = args.next() {
-                parse(filter, vars)?
+                parse(filter, vars, None)?
             } else {
-                Filter::default()
+                (Filter::default(), false)
             }
         }
     };
     //println!("Filter: {:?}", filter);
-    let files: Vec<_> = args.collect();
+    let files: Vec<_> = args.filter(|_| !cli.positional()).collect();
+    let mode = Mode::new(&cli, elements);
 
     let last = if files.is_empty() {
-        let inputs = read_buffered(&cli, io::stdin().lock());
+        let inputs = read_buffered(&cli, mode, io::stdin().lock());
         with_stdout(|out| run(&cli, &filter, ctx, inputs, |v| print(&cli, v, out)))?
-    } else {
+    } else if cli.in_place {
         let mut last = None;
         for file in files {
+            FILENAME.with(|f| *f.borrow_mut() = Val::str(file.clone()));
             let path = std::path::Path::new(file);
             let file = load_file(path).map_err(|e| Error::Io(Some(file.to_string()), e))?;
-            let inputs = read_slice(&cli, &file);
-            if cli.in_place {
-                // create a temporary file where output is written to
-                let location = path.parent().unwrap();
-                let mut tmp = tempfile::Builder::new()
-                    .prefix("jaq")
-                    .tempfile_in(location)?;
-
-                last = run(&cli, &filter, ctx.clone(), inputs, |output| {
-                    print(&cli, output, tmp.as_file_mut())
-                })?;
-
-                // replace the input file with the temporary file
-                let perms = std::fs::metadata(path)?.permissions();
-                tmp.persist(path).map_err(Error::Persist)?;
-                std::fs::set_permissions(path, perms)?;
+            let inputs = read_slice(&cli, mode.clone(), &file);
+
+            // create a temporary file where output is written to
+            let location = path.parent().unwrap();
+            let mut tmp = tempfile::Builder::new()
+                .prefix("jaq")
+                .tempfile_in(location)?;
+
+            let file_last = run(&cli, &filter, ctx.clone(), inputs, |output| {
+                print(&cli, output, tmp.as_file_mut())
+            })?;
+            // files without output do not affect the exit status
+            last = file_last.or(last);
+
+            // replace the input file with the temporary file
+            let perms = std::fs::metadata(path)?.permissions();
+            tmp.persist(path).map_err(Error::Persist)?;
+            std::fs::set_permissions(path, perms)?;
+        }
+        last
+    } else {
+        // the values of all files form a single stream of inputs,
+        // where we switch the file name when starting to read the next file;
+        // because we open every file only at that point,
+        // the values of preceding files are processed even if a file cannot be opened,
+        // and the contents of every file are dropped once its values have been read
+        let err = core::cell::Cell::new(None);
+        let inputs = files.iter().map_while(|file| {
+            FILENAME.with(|f| *f.borrow_mut() = Val::str((*file).clone()));
+            // pages of memory-mapped files stay resident once they have been read,
+            // so when we parse the elements of arrays one by one, we read files buffered
+            let inputs = if let Mode::Elements(_) = mode {
+                let file = std::fs::File::open(file);
+                file.map(|file| read_buffered(&cli, mode.clone(), io::BufReader::new(file)))
             } else {
-                last = with_stdout(|out| {
-                    run(&cli, &filter, ctx.clone(), inputs, |v| print(&cli, v, out))
-                })?;
+                load_file(Path::new(file)).map(|contents| read_slice(&cli, mode.clone(), contents))
+            };
+            match inputs {
+                Ok(inputs) => Some(inputs),
+                Err(e) => {
+                    err.set(Some(Error::Io(Some(file.to_string()), e)));
+                    None
+                }
             }
+        });
+        let inputs = inputs.flatten();
+        let last = with_stdout(|out| run(&cli, &filter, ctx, inputs, |v| print(&cli, v, out)))?;
+        if let Some(e) = err.take() {
+            return Err(e);
         }
         last
     };
@@ -215,6 +390,7 @@ fn binds(cli: &Cli) -> Result<Vec<(String, Val)>, Error> {
     bind(&mut var_val, &cli.arg, |v| {
         Ok(Val::Str(v.to_string().into()))
     })?;
+    bind(&mut var_val, &cli.argjson, |v| Ok(json_arg("--argjson", v)))?;
     bind(&mut var_val, &cli.rawfile, |f| {
         let s = std::fs::read_to_string(f).map_err(|e| Error::Io(Some(f.to_string()), e));
         Ok(Val::Str(s?.into()))
@@ -222,30 +398,135 @@ fn binds(cli: &Cli) -> Result<Vec<(String, Val)>, Error> {
     bind(&mut var_val, &cli.slurpfile, |f| {
         let path = std::path::Path::new(f);
         let file = load_file(path).map_err(|e| Error::Io(Some(f.to_string()), e))?;
-        Ok(Val::arr(json_slice(&file).collect::<Result<Vec<_>, _>>()?))
+        Ok(Val::arr(
+            json_slice(&file, Mode::Values).collect::<Result<Vec<_>, _>>()?,
+        ))
     })?;
 
-    var_val.push(("ARGS".to_string(), args_named(&var_val)));
+    let rest = cli.rest().iter();
+    let positional = if cli.json_args {
+        rest.map(|v| json_arg("--jsonargs", v)).collect()
+    } else if cli.str_args {
+        rest.map(|v| Val::str(v.clone())).collect()
+    } else {
+        Vec::new()
+    };
+    let args = args(positional, &var_val);
+    var_val.push(("ARGS".to_string(), args));
     let env = std::env::vars().map(|(k, v)| (k.into(), Val::str(v)));
     var_val.push(("ENV".to_string(), Val::obj(env.collect())));
 
     Ok(var_val)
 }
 
-fn args_named(var_val: &[(String, Val)]) -> Val {
+/// Parse a single JSON value given as argument to `opt`, failing with a usage error.
+fn json_arg(opt: &str, v: &str) -> Val {
+    let mut vals = json_slice(v.as_bytes(), Mode::Values);
+    match (vals.next(), vals.next()) {
+        (Some(Ok(v)), None) => v,
+        _ => {
+            use clap::{error::ErrorKind, CommandFactory};
+            let msg = format!("invalid JSON value `{v}` passed to `{opt}`");
+            Cli::command().error(ErrorKind::InvalidValue, msg).exit()
+        }
+    }
+}
+
+fn args(positional: Vec<Val>, var_val: &[(String, Val)]) -> Val {
     let named = var_val
         .iter()
         .map(|(var, val)| (var.clone().into(), val.clone()));
-    let args = std::iter::once(("named".to_string().into(), Val::obj(named.collect())));
-    Val::obj(args.collect())
+    let args = [
+        ("positional".to_string().into(), Val::arr(positional)),
+        ("named".to_string().into(), Val::obj(named.collect())),
+    ];
+    Val::obj(args.into_iter().collect())
+}
+
+/// Return the directories where modules are searched.
+fn search_path(cli: &Cli) -> Vec<PathBuf> {
+    let mut search = cli.library_path.clone();
+    if let Some(paths) = std::env::var_os("JAQ_LIBRARY_PATH") {
+        search.extend(std::env::split_paths(&paths));
+    }
+    if search.is_empty() {
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        search.extend(home.map(|home| home.join(".jq")));
+        let exe = std::env::current_exe().ok();
+        if let Some(origin) = exe.as_ref().and_then(|exe| exe.parent()) {
+            search.push(origin.join("../lib/jq"));
+            search.push(origin.join("../lib"));
+        }
+    }
+    search
 }
 
-fn parse(filter_str: &str, vars: Vec<String>) -> Result<Filter, Vec<ParseError>> {
+/// Return a context with all named filters available in the CLI.
+///
+/// If `inputs` is false, then the filters that read inputs are omitted, namely
+/// `input`, `inputs`, and `input_line_number`.
+fn parse_ctx(vars: Vec<String>, inputs: bool) -> ParseCtx {
     let mut defs = ParseCtx::new(vars);
-    defs.insert_natives(jaq_core::core());
-    defs.insert_defs(jaq_std::std());
+    let mut natives: Vec<_> = jaq_core::core().collect();
+    // read the line number only once the output is demanded, e.g. after `input`
+    let line = Native::new(|_, _| {
+        Box::new(core::iter::once_with(|| {
+            Ok(Val::Int(LINE.with(|l| l.get()) as isize))
+        }))
+    });
+    natives.push(("input_line_number".to_string(), 0, line));
+    let file = Native::new(|_, _| {
+        Box::new(core::iter::once_with(|| {
+            Ok(FILENAME.with(|f| f.borrow().clone()))
+        }))
+    });
+    natives.push(("input_filename".to_string(), 0, file));
+    let meta = Native::new(|_, cv| box_once(SEARCH.with(|s| load::modulemeta(&s.borrow(), cv.1))));
+    natives.push(("modulemeta".to_string(), 0, meta));
+    let mut std = jaq_std::std();
+    if !inputs {
+        let reads = |name: &str| matches!(name, "input" | "inputs" | "input_line_number");
+        natives.retain(|(name, ..)| !reads(name));
+        std.retain(|def| !reads(&def.lhs.name));
+    }
+
+    let natives_sig = natives
+        .iter()
+        .map(|(name, arity, _)| (name.as_str(), *arity));
+    let std_sig = std
+        .iter()
+        .map(|def| (def.lhs.name.as_str(), def.lhs.args.len()));
+    let builtins = builtins(natives_sig.chain(std_sig));
+
+    defs.insert_natives(natives);
+    defs.insert_defs(std);
+    defs.insert_defs([builtins]);
     assert!(defs.errs.is_empty());
-    let (filter, errs) = jaq_parse::parse(filter_str, jaq_parse::main());
+    defs
+}
+
+/// Parse and compile a filter, where `path` is the file containing the filter, if any.
+///
+/// Modules imported by the filter are searched relative to the directory of `path`.
+///
+/// Also return whether the filter starts with `.[]` and does not read inputs,
+/// such that it can be run on the [`elements`] of top-level arrays one by one.
+fn parse(
+    filter_str: &str,
+    vars: Vec<String>,
+    path: Option<&Path>,
+) -> Result<(Filter, bool), Vec<ParseError>> {
+    // check definitions of modules, such that we can report errors with the module source
+    let check = |defs| {
+        let mut ctx = parse_ctx(vars.clone(), true);
+        let body = (jaq_syn::filter::Filter::Id, 0..0);
+        ctx.compile(jaq_syn::Main { defs, body });
+        let errs = ctx.errs.into_iter();
+        errs.map(|(e, span)| (e.to_string(), span)).collect()
+    };
+    let mut defs = parse_ctx(vars.clone(), true);
+    let parser = jaq_parse::module(jaq_parse::main());
+    let (module, errs) = jaq_parse::parse(filter_str, parser);
     if !errs.is_empty() {
         return Err(errs
             .into_iter()
@@ -255,9 +536,19 @@ fn parse(filter_str: &str, vars: Vec<String>) -> Result<Filter, Vec<ParseError>>
             })
             .collect());
     }
-    let filter = defs.compile(filter.unwrap());
+    let mut loader = load::Loader::new(SEARCH.with(|s| s.borrow().clone()), &check);
+    let main = loader.main(path, filter_str, module.unwrap())?;
+    // the filter does not read inputs if it compiles without the filters that read inputs
+    let elements = elements::starts_with_iter(&main.body.0) && {
+        let mut ctx = parse_ctx(vars.clone(), false);
+        ctx.insert_defs(loader.defs.clone());
+        ctx.compile(main.clone());
+        ctx.errs.is_empty()
+    };
+    defs.insert_defs(loader.defs);
+    let filter = defs.compile(main);
     if defs.errs.is_empty() {
-        Ok(filter)
+        Ok((filter, elements))
     } else {
         Err(defs
             .errs
@@ -270,12 +561,47 @@ fn parse(filter_str: &str, vars: Vec<String>) -> Result<Filter, Vec<ParseError>>
     }
 }
 
+/// Return a definition of `builtins`, which yields
+/// the sorted names and arities of the given filters and of `builtins` itself,
+/// such as `["add/0", "builtins/0", ..., "map/1", ...]`.
+///
+/// Like in jq, formats such as `@base64` are not included.
+fn builtins<'a>(sigs: impl Iterator<Item = (&'a str, usize)>) -> jaq_syn::Def {
+    let sigs = sigs.filter(|(name, _)| !name.starts_with('@'));
+    let sigs = sigs.chain([("builtins", 0)]);
+    let mut names: Vec<_> = sigs
+        .map(|(name, arity)| format!("{name}/{arity}"))
+        .collect();
+    names.sort();
+    names.dedup();
+    let names = serde_json::to_string(&names).unwrap();
+    let def = format!("def builtins: {names};");
+    let (defs, errs) = jaq_parse::parse(&def, jaq_parse::defs());
+    assert!(errs.is_empty());
+    defs.unwrap().remove(0)
+}
+
+/// Contents of a file, which are memory-mapped if possible.
+enum Contents {
+    Mmap(memmap2::Mmap),
+    Read(Vec<u8>),
+}
+
+impl AsRef<[u8]> for Contents {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            Self::Mmap(mmap) => mmap,
+            Self::Read(bytes) => bytes,
+        }
+    }
+}
+
 /// Try to load file by memory mapping and fall back to regular loading if it fails.
-fn load_file(path: &std::path::Path) -> io::Result<Box<dyn core::ops::Deref<Target = [u8]>>> {
+fn load_file(path: &std::path::Path) -> io::Result<Contents> {
     let file = std::fs::File::open(path)?;
     match unsafe { memmap2::Mmap::map(&file) } {
-        Ok(mmap) => Ok(Box::new(mmap)),
-        Err(_) => Ok(Box::new(std::fs::read(path)?)),
+        Ok(mmap) => Ok(Contents::Mmap(mmap)),
+        Err(_) => Ok(Contents::Read(std::fs::read(path)?)),
     }
 }
 
@@ -283,41 +609,159 @@ fn invalid_data(e: impl std::error::Error + Send + Sync + 'static) -> std::io::E
     io::Error::new(io::ErrorKind::InvalidData, e)
 }
 
-fn json_slice(slice: &[u8]) -> impl Iterator<Item = io::Result<Val>> + '_ {
-    let mut lexer = hifijson::SliceLexer::new(slice);
+/// What to parse from JSON input.
+#[derive(Clone)]
+enum Mode {
+    /// whole values
+    Values,
+    /// events of values, like `tostream` yields them (for `--stream`)
+    Stream(stream::Stream),
+    /// elements of top-level arrays (for filters that start with `.[]`)
+    Elements(elements::Elements),
+}
+
+impl Mode {
+    /// Return the mode for the given options, where
+    /// `elements` is whether the filter may be run on elements of top-level arrays.
+    fn new(cli: &Cli, elements: bool) -> Self {
+        if cli.stream {
+            Self::Stream(Default::default())
+        } else if elements && !(cli.slurp || cli.null_input || cli.seq) {
+            Self::Elements(Default::default())
+        } else {
+            Self::Values
+        }
+    }
+}
+
+/// Parse the next value, event, or element, depending on `mode`.
+fn json_next<L: hifijson::LexAlloc>(
+    lexer: &mut L,
+    mode: &mut Mode,
+) -> Option<Result<Val, hifijson::Error>> {
+    match mode {
+        Mode::Values => Some(Val::parse(lexer.ws_token()?, lexer)),
+        Mode::Stream(stream) => stream.next(lexer),
+        Mode::Elements(elements) => elements.next(lexer),
+    }
+}
+
+/// Parse values from a slice, which may be owned by the returned iterator.
+fn json_slice<'a>(
+    slice: impl AsRef<[u8]> + 'a,
+    mut mode: Mode,
+) -> impl Iterator<Item = io::Result<Val>> + 'a {
+    // position up to which we have parsed
+    let mut pos = 0;
+    // position after the last newline that we counted
+    let mut counted = 0;
+    LINE.with(|l| l.set(0));
     core::iter::from_fn(move || {
-        use hifijson::token::Lex;
-        Some(Val::parse(lexer.ws_token()?, &mut lexer).map_err(invalid_data))
+        let slice = slice.as_ref();
+        let mut lexer = hifijson::SliceLexer::new(&slice[pos..]);
+        let v = json_next(&mut lexer, &mut mode)?.map_err(invalid_data);
+        // count the lines up to the end of the line where the value ends
+        let end = slice.len() - lexer.as_slice().len();
+        pos = end;
+        if end > counted {
+            let eol = slice[end..].iter().position(|c| *c == b'\n');
+            let eol = eol.map_or(slice.len(), |i| end + i + 1);
+            let newlines = slice[counted..eol].iter().filter(|c| **c == b'\n');
+            LINE.with(|l| l.set(l.get() + newlines.count()));
+            counted = eol;
+        }
+        Some(v)
     })
 }
 
-fn json_read<'a>(read: impl BufRead + 'a) -> impl Iterator<Item = io::Result<Val>> + 'a {
-    let mut lexer = hifijson::IterLexer::new(read.bytes());
+fn json_read<'a>(
+    read: impl BufRead + 'a,
+    mut mode: Mode,
+) -> impl Iterator<Item = io::Result<Val>> + 'a {
+    let bytes = read_chunks(read).flat_map(|chunk| {
+        let (chunk, err) = match chunk {
+            Ok(chunk) => (chunk, None),
+            Err(e) => (Vec::new(), Some(Err(e))),
+        };
+        chunk.into_iter().map(Ok).chain(err)
+    });
+    let mut lexer = hifijson::IterLexer::new(bytes);
     core::iter::from_fn(move || {
-        use hifijson::token::Lex;
-        let v = Val::parse(lexer.ws_token()?, &mut lexer);
+        let v = json_next(&mut lexer, &mut mode)?;
         Some(v.map_err(|e| core::mem::take(&mut lexer.error).unwrap_or_else(|| invalid_data(e))))
     })
 }
 
-fn read_buffered<'a, R>(cli: &Cli, read: R) -> Box<dyn Iterator<Item = io::Result<Val>> + 'a>
+/// Record separator that precedes every value in JSON text sequences.
+const RS: u8 = 0x1E;
+
+/// Read values from a JSON text sequence, where
+/// every record (delimited by record separators) may contain any number of values.
+///
+/// If a record cannot be parsed, then we warn and skip the remainder of the record.
+fn json_seq<'a>(read: impl BufRead + 'a, mode: Mode) -> impl Iterator<Item = io::Result<Val>> + 'a {
+    let mut lines = 0;
+    read.split(RS).flat_map(move |record| {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => return Vec::from([Err(e)]),
+        };
+        let mut vals = Vec::new();
+        for v in json_slice(&record, mode.clone()) {
+            match v {
+                Ok(v) => vals.push(Ok(v)),
+                Err(e) => {
+                    eprintln!("Warning: ignoring parse error: {e}");
+                    break;
+                }
+            }
+        }
+        lines += record.iter().filter(|c| **c == b'\n').count();
+        LINE.with(|l| l.set(lines));
+        vals
+    })
+}
+
+fn read_buffered<'a, R>(
+    cli: &Cli,
+    mode: Mode,
+    read: R,
+) -> Box<dyn Iterator<Item = io::Result<Val>> + 'a>
 where
     R: BufRead + 'a,
 {
     if cli.raw_input {
         Box::new(raw_input(cli.slurp, read).map(|r| r.map(Val::str)))
+    } else if cli.seq {
+        let vals = json_seq(read, mode);
+        Box::new(collect_if(cli.slurp, vals, Val::arr))
     } else {
-        let vals = json_read(read);
+        #[cfg(feature = "simd-json")]
+        if let Mode::Values = mode {
+            return Box::new(collect_if(cli.slurp, simd::ndjson(read), Val::arr));
+        }
+        let vals = json_read(read, mode);
         Box::new(collect_if(cli.slurp, vals, Val::arr))
     }
 }
 
-fn read_slice<'a>(cli: &Cli, slice: &'a [u8]) -> Box<dyn Iterator<Item = io::Result<Val>> + 'a> {
+fn read_slice<'a, S>(
+    cli: &Cli,
+    mode: Mode,
+    slice: S,
+) -> Box<dyn Iterator<Item = io::Result<Val>> + 'a>
+where
+    S: AsRef<[u8]> + 'a,
+{
     if cli.raw_input {
-        let read = io::BufReader::new(slice);
+        let read = io::Cursor::new(slice);
         Box::new(raw_input(cli.slurp, read).map(|r| r.map(Val::str)))
+    } else if cli.seq {
+        let vals = json_seq(io::Cursor::new(slice), mode);
+        Box::new(collect_if(cli.slurp, vals, Val::arr))
     } else {
-        let vals = json_slice(slice);
+        // unlike for standard input, simd-json is not faster than our parser here
+        let vals = json_slice(slice, mode);
         Box::new(collect_if(cli.slurp, vals, Val::arr))
     }
 }
@@ -329,12 +773,85 @@ where
     if slurp {
         let mut buf = String::new();
         let s = read.read_to_string(&mut buf).map(|_| buf);
+        let newlines = s.as_ref().map_or(0, |s| s.matches('\n').count());
+        LINE.with(|l| l.set(newlines));
         Box::new(std::iter::once(s))
     } else {
-        Box::new(read.lines()) as Box<dyn Iterator<Item = _>>
+        let lines = read_lines(read).map(|line| {
+            let mut line = line?;
+            if line.last() == Some(&b'\n') {
+                line.pop();
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+            }
+            String::from_utf8(line).map_err(invalid_data)
+        });
+        Box::new(lines) as Box<dyn Iterator<Item = _>>
     }
 }
 
+thread_local! {
+    /// Number of lines read from the current input, as returned by `input_line_number`
+    static LINE: core::cell::Cell<usize> = const { core::cell::Cell::new(0) };
+    /// Name of the current input file, or `null` for standard input,
+    /// as returned by `input_filename`
+    static FILENAME: core::cell::RefCell<Val> = const { core::cell::RefCell::new(Val::Null) };
+    /// Directories where modules are searched, as given by `search_path`
+    static SEARCH: core::cell::RefCell<Vec<PathBuf>> = const { core::cell::RefCell::new(Vec::new()) };
+}
+
+/// Read lines (including their newline characters) and count them in `LINE`.
+///
+/// This counts a line as soon as it is read, like jq does.
+/// For example, when reading `1 2\n3`, jq's `input_line_number`
+/// yields 1 for the input values `1` and `2`, and also for `3`,
+/// because the last line does not end with a newline.
+fn read_lines<'a>(mut read: impl BufRead + 'a) -> impl Iterator<Item = io::Result<Vec<u8>>> + 'a {
+    LINE.with(|l| l.set(0));
+    core::iter::from_fn(move || {
+        let mut line = Vec::new();
+        match read.read_until(b'\n', &mut line) {
+            Ok(0) => None,
+            Ok(_) => {
+                if line.last() == Some(&b'\n') {
+                    LINE.with(|l| l.set(l.get() + 1));
+                }
+                Some(Ok(line))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    })
+}
+
+/// Read chunks that end with a newline or with the contents of the read buffer,
+/// counting lines like [`read_lines`].
+///
+/// Unlike [`read_lines`], this does not hold whole lines in memory,
+/// which matters for large JSON values that are written on a single line.
+fn read_chunks<'a>(mut read: impl BufRead + 'a) -> impl Iterator<Item = io::Result<Vec<u8>>> + 'a {
+    LINE.with(|l| l.set(0));
+    core::iter::from_fn(move || {
+        let buf = match read.fill_buf() {
+            Ok(buf) => buf,
+            Err(e) => return Some(Err(e)),
+        };
+        let len = buf
+            .iter()
+            .position(|c| *c == b'\n')
+            .map_or(buf.len(), |i| i + 1);
+        if len == 0 {
+            return None;
+        }
+        let chunk = buf[..len].to_vec();
+        read.consume(len);
+        if chunk.last() == Some(&b'\n') {
+            LINE.with(|l| l.set(l.get() + 1));
+        }
+        Some(Ok(chunk))
+    })
+}
+
 fn collect_if<'a, T: 'a, E: 'a>(
     slurp: bool,
     iter: impl Iterator<Item = Result<T, E>> + 'a,
@@ -415,6 +932,71 @@ impl From<Vec<ParseError>> for Error {
     }
 }
 
+fn print_json(cli: &Cli, val: Val, writer: &mut impl Write) -> io::Result<()> {
+    use colored_json::{ColoredFormatter, CompactFormatter, PrettyFormatter};
+    let val = if cli.sort_keys { sort_keys(val) } else { val };
+    let val = json_value(val);
+    let mode = cli.color_mode();
+    let indent = if cli.tab {
+        String::from("\t")
+    } else {
+        " ".repeat(cli.indent.min(7))
+    };
+
+    // this looks ugly, but it is hard to abstract over the `Formatter` because
+    // we cannot create a `Box<dyn Formatter>` because
+    // Rust says that the `Formatter` trait is not "object safe"
+    if cli.compact_output {
+        ColoredFormatter::with_styler(CompactFormatter, color::styler())
+            .write_colored_json(&val, writer, mode)
+    } else {
+        let pretty = PrettyFormatter::with_indent(indent.as_bytes());
+        ColoredFormatter::with_styler(pretty, color::styler())
+            .write_colored_json(&val, writer, mode)
+    }?;
+    Ok(())
+}
+
+/// Convert a value to JSON, writing negative zero as `-0` like jq.
+///
+/// Older versions of `serde_json` parse `-0` as `0`, so
+/// we construct this number without parsing it.
+/// This relies on the `arbitrary_precision` feature of `serde_json`.
+fn json_value(val: Val) -> serde_json::Value {
+    use serde_json::{Number, Value};
+    match val {
+        Val::Float(f) if f == 0.0 && f.is_sign_negative() => {
+            Value::Number(Number::from_string_unchecked("-0".into()))
+        }
+        Val::Arr(a) => Value::Array(a.iter().map(|x| json_value(x.clone())).collect()),
+        Val::Obj(o) => Value::Object(
+            o.iter()
+                .map(|(k, v)| ((**k).clone(), json_value(v.clone())))
+                .collect(),
+        ),
+        _ => val.into(),
+    }
+}
+
+/// Escape all non-ASCII characters in a JSON text, such as `é` as `\u00e9`.
+///
+/// Characters outside the Basic Multilingual Plane are escaped as surrogate pairs.
+/// This is correct because non-ASCII characters in JSON can only occur in strings.
+fn ascii(json: &str) -> String {
+    let mut out = String::with_capacity(json.len());
+    for c in json.chars() {
+        if c.is_ascii() {
+            out.push(c);
+        } else {
+            let mut buf = [0; 2];
+            for u in c.encode_utf16(&mut buf) {
+                out.push_str(&format!("\\u{u:04x}"));
+            }
+        }
+    }
+    out
+}
+
 /// Run a filter with given input values and run `f` for every value output.
 ///
 /// This function cannot return an `Iterator` because it creates an `RcIter`.
@@ -435,7 +1017,7 @@ fn run(
     let iter = RcIter::new(iter);
     let null = RcIter::new(null);
 
-    let ctx = Ctx::new(vars, &iter);
+    let ctx = Ctx::new(vars, &iter).with_recursion_limit(cli.recursion_limit);
 
     for item in if cli.null_input { &null } else { &iter } {
         let input = item.map_err(Error::Parse)?;
@@ -450,33 +1032,42 @@ fn run(
 }
 
 fn print(cli: &Cli, val: Val, writer: &mut impl Write) -> io::Result<()> {
-    use colored_json::{ColoredFormatter, CompactFormatter, PrettyFormatter};
+    if cli.seq {
+        writer.write_all(&[RS])?;
+    }
     match val {
-        Val::Str(s) if cli.raw_output => write!(writer, "{s}")?,
-        _ => {
-            let val = serde_json::Value::from(val);
-            let mode = cli.color_mode();
-            let indent = if cli.tab {
-                String::from("\t")
-            } else {
-                " ".repeat(cli.indent)
-            };
-
-            // this looks ugly, but it is hard to abstract over the `Formatter` because
-            // we cannot create a `Box<dyn Formatter>` because
-            // Rust says that the `Formatter` trait is not "object safe"
-            if cli.compact_output {
-                ColoredFormatter::new(CompactFormatter).write_colored_json(&val, writer, mode)
-            } else {
-                ColoredFormatter::new(PrettyFormatter::with_indent(indent.as_bytes()))
-                    .write_colored_json(&val, writer, mode)
-            }?;
+        Val::Str(s) if cli.raw_output || cli.join_output => write!(writer, "{s}")?,
+        _ if cli.ascii_output => {
+            let mut json = Vec::new();
+            print_json(cli, val, &mut json)?;
+            let json = String::from_utf8(json).map_err(invalid_data)?;
+            writer.write_all(ascii(&json).as_bytes())?;
         }
+        _ => print_json(cli, val, writer)?,
     };
-    if !cli.join_output {
+    if !cli.join_output || cli.seq {
         writeln!(writer)?;
     }
-    Ok(())
+    // show every value as soon as it is produced, even if it does not end with a newline
+    writer.flush()
+}
+
+/// Sort the keys of all objects in a value, recursively.
+fn sort_keys(v: Val) -> Val {
+    let sort = |v: &mut Val| *v = sort_keys(core::mem::replace(v, Val::Null));
+    match v {
+        Val::Arr(mut a) => {
+            std::rc::Rc::make_mut(&mut a).iter_mut().for_each(sort);
+            Val::Arr(a)
+        }
+        Val::Obj(mut o) => {
+            let o_ = std::rc::Rc::make_mut(&mut o);
+            o_.sort_keys();
+            o_.values_mut().for_each(sort);
+            Val::Obj(o)
+        }
+        v => v,
+    }
 }
 
 fn with_stdout<T>(f: impl FnOnce(&mut io::StdoutLock) -> Result<T, Error>) -> Result<T, Error> {
@@ -558,7 +1149,7 @@ fn run_test(test: jaq_syn::test::Test<String>) -> Result<(Val, Val), Error> {
     let inputs = RcIter::new(Box::new(core::iter::empty()));
     let ctx = Ctx::new(Vec::new(), &inputs);
 
-    let filter = parse(&test.filter, Vec::new())?;
+    let (filter, _) = parse(&test.filter, Vec::new(), None)?;
 
     use hifijson::token::Lex;
     let json = |s: String| {
@@ -0,0 +1,113 @@
+//! Parsing of newline-delimited JSON with simd-json.
+//!
+//! This is only used if jaq is compiled with the `simd-json` feature.
+//! Because simd-json parses only complete lines,
+//! no value of a line is yielded before the whole line has been read.
+//! To yield the same values as the default parser,
+//! we use simd-json only for lines that contain exactly one value and
+//! that can be converted to a value without loss.
+//! In particular, lines with fractional numbers (such as `1.10`)
+//! are parsed with the default parser in order to preserve their decimal representation.
+
+use super::{json_read, LINE};
+use jaq_interpret::Val;
+use simd_json::{Buffers, Node, StaticNode};
+use std::io::{self, BufRead, Read};
+use std::rc::Rc;
+
+/// Parse values, assuming that every line contains exactly one value.
+///
+/// If this assumption fails, for example because a value spans multiple lines,
+/// then we parse the input from that line onwards with the default parser.
+pub fn ndjson<'a>(read: impl BufRead + 'a) -> impl Iterator<Item = io::Result<Val>> + 'a {
+    LINE.with(|l| l.set(0));
+    let mut buffers = Buffers::default();
+    let mut read = Some(read);
+    let mut rest: Option<Box<dyn Iterator<Item = _>>> = None;
+    core::iter::from_fn(move || loop {
+        if let Some(rest) = &mut rest {
+            return rest.next();
+        }
+        let mut line = Vec::new();
+        match read.as_mut()?.read_until(b'\n', &mut line) {
+            Ok(0) => return None,
+            Ok(_) => (),
+            Err(e) => return Some(Err(e)),
+        }
+        let lines = LINE.with(|l| l.get());
+        if line.last() == Some(&b'\n') {
+            LINE.with(|l| l.set(lines + 1));
+        }
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        match parse(&line, &mut buffers) {
+            Some(v) => return Some(Ok(v)),
+            None => {
+                let read = read.take()?;
+                rest = Some(Box::new(json_read(
+                    io::Cursor::new(line).chain(read),
+                    false,
+                )));
+                // the default parser counts the current line again
+                LINE.with(|l| l.set(lines));
+            }
+        }
+    })
+}
+
+/// Parse a line that contains exactly one value, returning `None` if this fails.
+fn parse(line: &[u8], buffers: &mut Buffers) -> Option<Val> {
+    // simd-json modifies its input, so we have to copy it
+    let mut bytes = line.to_vec();
+    let tape = simd_json::to_tape_with_buffers(&mut bytes, buffers).ok()?;
+    if tape
+        .0
+        .iter()
+        .any(|n| matches!(n, Node::Static(StaticNode::F64(_))))
+    {
+        let mut lexer = hifijson::SliceLexer::new(line);
+        use hifijson::token::Lex;
+        return Val::parse(lexer.ws_token()?, &mut lexer).ok();
+    }
+    val(&mut tape.0.iter(), Val::PARSE_DEPTH)
+}
+
+/// Convert the nodes of a tape, starting with the root node, to a value.
+///
+/// Values nested more deeply than `depth` yield `None`,
+/// so that the default parser reports them.
+fn val<'a>(nodes: &mut impl Iterator<Item = &'a Node<'a>>, depth: usize) -> Option<Val> {
+    Some(match nodes.next()? {
+        Node::String(s) => Val::str(s.to_string()),
+        Node::Array { len, .. } => {
+            let depth = depth.checked_sub(1)?;
+            Val::arr(
+                (0..*len)
+                    .map(|_| val(nodes, depth))
+                    .collect::<Option<_>>()?,
+            )
+        }
+        Node::Object { len, .. } => {
+            let depth = depth.checked_sub(1)?;
+            let kv = |_| match nodes.next()? {
+                Node::String(k) => Some((Rc::new(k.to_string()), val(nodes, depth)?)),
+                _ => None,
+            };
+            Val::obj((0..*len).map(kv).collect::<Option<_>>()?)
+        }
+        Node::Static(StaticNode::Null) => Val::Null,
+        Node::Static(StaticNode::Bool(b)) => Val::Bool(*b),
+        Node::Static(StaticNode::I64(i)) => int(*i),
+        Node::Static(StaticNode::U64(u)) => int(*u),
+        Node::Static(StaticNode::F64(_)) => return None,
+    })
+}
+
+/// Convert an integer to a value, like the default parser does.
+fn int<T: Copy + ToString>(i: T) -> Val
+where
+    isize: TryFrom<T>,
+{
+    isize::try_from(i).map_or_else(|_| Val::Num(Rc::new(i.to_string())), Val::Int)
+}
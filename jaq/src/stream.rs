@@ -0,0 +1,90 @@
+//! Parsing of JSON values to streams of events, like `tostream` yields them.
+//!
+//! For example, `{"a": [1, 2]}` yields the events
+//! `[["a", 0], 1]`, `[["a", 1], 2]`, `[["a", 1]]`, and `[["a"]]`.
+//! This allows processing values without holding them in memory entirely.
+
+use hifijson::token::{Expect, Token};
+use hifijson::{Error, LexAlloc};
+use jaq_interpret::Val;
+
+/// Position of the parser between two events.
+#[derive(Clone, Default)]
+pub struct Stream {
+    /// path to the most recently parsed value, consisting of array indices and object keys
+    ///
+    /// If this is empty, then we are between two top-level values.
+    path: Vec<Val>,
+}
+
+impl Stream {
+    /// Parse the next event, returning `None` if there is no more input.
+    pub fn next<L: LexAlloc>(&mut self, lexer: &mut L) -> Option<Result<Val, Error>> {
+        if self.path.is_empty() {
+            let token = lexer.ws_token()?;
+            Some(self.value(token, lexer))
+        } else {
+            Some(self.rest(lexer))
+        }
+    }
+
+    /// Parse the rest of the innermost array or object after a value of it.
+    ///
+    /// This yields either the first leaf of the next value or
+    /// the closing event of the array or object.
+    fn rest<L: LexAlloc>(&mut self, lexer: &mut L) -> Result<Val, Error> {
+        let token = lexer.ws_token().ok_or(Expect::CommaOrEnd)?;
+        match (token, self.path.last_mut()) {
+            (Token::Comma, Some(Val::Int(i))) => *i += 1,
+            (Token::Comma, Some(k @ Val::Str(_))) => {
+                *k = key(lexer.ws_token().ok_or(Expect::String)?, lexer)?;
+            }
+            (Token::RSquare, Some(Val::Int(_))) | (Token::RCurly, Some(Val::Str(_))) => {
+                let event = Val::arr(Vec::from([Val::arr(self.path.clone())]));
+                self.path.pop();
+                return Ok(event);
+            }
+            _ => Err(Expect::CommaOrEnd)?,
+        }
+        let token = lexer.ws_token().ok_or(Expect::Value)?;
+        self.value(token, lexer)
+    }
+
+    /// Parse a value up to its first leaf and yield the event for this leaf.
+    ///
+    /// We use a loop instead of recursion here,
+    /// so that deeply nested values cannot overflow the stack.
+    fn value<L: LexAlloc>(&mut self, mut token: Token, lexer: &mut L) -> Result<Val, Error> {
+        loop {
+            match token {
+                Token::LSquare => {
+                    token = lexer.ws_token().ok_or(Expect::ValueOrEnd)?;
+                    if token == Token::RSquare {
+                        return Ok(self.leaf(Val::arr(Vec::new())));
+                    }
+                    self.path.push(Val::Int(0));
+                }
+                Token::LCurly => {
+                    token = lexer.ws_token().ok_or(Expect::ValueOrEnd)?;
+                    if token == Token::RCurly {
+                        return Ok(self.leaf(Val::obj(Default::default())));
+                    }
+                    self.path.push(key(token, lexer)?);
+                    token = lexer.ws_token().ok_or(Expect::Value)?;
+                }
+                _ => return Ok(self.leaf(Val::parse(token, lexer)?)),
+            }
+        }
+    }
+
+    /// Return the event for a leaf at the current path.
+    fn leaf(&self, v: Val) -> Val {
+        Val::arr(Vec::from([Val::arr(self.path.clone()), v]))
+    }
+}
+
+/// Parse an object key, followed by a colon.
+fn key<L: LexAlloc>(token: Token, lexer: &mut L) -> Result<Val, Error> {
+    let key = lexer.str_colon(token, |lexer| lexer.str_string().map_err(Error::Str))?;
+    Ok(Val::str(key.to_string()))
+}
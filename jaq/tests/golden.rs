@@ -1,15 +1,35 @@
 use std::{env, io, process, str};
 
-fn golden_test(args: &[&str], input: &str, out_ex: &str) -> io::Result<()> {
+/// Run jaq with the given arguments on the given input.
+fn run(args: &[&str], input: &str) -> io::Result<process::Output> {
+    run_env(args, &[], input)
+}
+
+/// Run jaq with the given arguments and environment variables on the given input.
+///
+/// Other environment variables that affect the output, such as `JQ_COLORS`, are removed.
+fn run_env(args: &[&str], vars: &[(&str, &str)], input: &str) -> io::Result<process::Output> {
     let mut child = process::Command::new(env!("CARGO_BIN_EXE_jaq"))
         .args(args)
+        .env_remove("JQ_COLORS")
+        .env_remove("NO_COLOR")
+        .envs(vars.iter().copied())
         .stdin(process::Stdio::piped())
         .stdout(process::Stdio::piped())
+        .stderr(process::Stdio::piped())
         .spawn()?;
 
     use io::Write;
-    child.stdin.take().unwrap().write_all(input.as_bytes())?;
-    let output = child.wait_with_output()?;
+    // jaq may exit without reading its input, for example on invalid arguments
+    match child.stdin.take().unwrap().write_all(input.as_bytes()) {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e),
+        _ => (),
+    }
+    child.wait_with_output()
+}
+
+fn golden_test(args: &[&str], input: &str, out_ex: &str) -> io::Result<()> {
+    let output = run(args, input)?;
     assert!(output.status.success());
 
     let out_act = str::from_utf8(&output.stdout).expect("invalid UTF-8 in output");
@@ -34,8 +54,43 @@ macro_rules! test {
 
 test!(no_args, &[], "[0, 1]", "[\n  0,\n  1\n]");
 test!(one, &["1"], "0", "1");
+test!(indent, &["--indent", "9"], "[0]", "[\n       0\n]");
+test!(
+    tab,
+    &["--tab"],
+    "{\"a\": [0]}",
+    "{\n\t\"a\": [\n\t\t0\n\t]\n}"
+);
 test!(sparse, &["."], "[2,3]", "[\n  2,\n  3\n]");
 
+// JSON inside of values is always compact, regardless of output formatting
+test!(
+    json_compact,
+    &["--indent", "4", ".[] | @json, tojson, \"\\(.)\""],
+    "[{\"a\": [1]}]",
+    r#""{\"a\":[1]}"
+"{\"a\":[1]}"
+"{\"a\":[1]}""#
+);
+
+// floats are printed like jq prints them
+test!(
+    float_output,
+    &["-c", "map(. * 1), [infinite, -infinite, nan]"],
+    "[1.0, 1e100, 0.1, -0.0, 1e-7, 0.00012, 1e15, 1e16, 1.5e16, 1e-5, 123.456]",
+    "[1,1e+100,0.1,-0,1e-07,0.00012,1000000000000000,1e+16,15000000000000000,1e-05,123.456]
+[1.7976931348623157e+308,-1.7976931348623157e+308,null]"
+);
+
+// integers that do not fit into `isize` keep their decimal representation,
+// unless they are used in arithmetic
+test!(
+    big_int,
+    &["-c", "[12345678901234567890, ., 12345678901234567890 + 0]"],
+    "123456789012345678901234567890",
+    "[12345678901234567890,123456789012345678901234567890,12345678901234567000]"
+);
+
 test!(
     arg,
     &["--arg", "x", "y", "--arg", "a", "b", "$x + $a"],
@@ -43,6 +98,88 @@ test!(
     "\"yb\""
 );
 
+test!(arg_hi, &["--arg", "x", "hi", "$x"], "0", "\"hi\"");
+
+// `--arg` never parses its value as JSON
+test!(
+    arg_named,
+    &["-nc", "--arg", "x", "5", "$x, $ARGS.named"],
+    "",
+    r#""5"
+{"x":"5"}"#
+);
+
+test!(
+    argjson,
+    &["-c", "--argjson", "x", r#"{"a": 1}"#, "$x, $x.a"],
+    "0",
+    "{\"a\":1}\n1"
+);
+
+#[test]
+fn argjson_invalid() -> io::Result<()> {
+    let output = run(&["--argjson", "x", "not json", "$x"], "0")?;
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+    let stderr = str::from_utf8(&output.stderr).expect("invalid UTF-8 in output");
+    assert!(stderr.contains("--argjson"));
+    Ok(())
+}
+
+test!(
+    args,
+    &["-nc", "--argjson", "x", "[1]", "$ARGS", "--args", "a", "b"],
+    "",
+    r#"{"positional":["a","b"],"named":{"x":[1]}}"#
+);
+
+// `$ARGS` is defined even if no arguments are given
+test!(
+    args_empty,
+    &["-nc", "$ARGS"],
+    "",
+    r#"{"positional":[],"named":{}}"#
+);
+
+test!(
+    args_named,
+    &[
+        "-nc",
+        "--arg",
+        "a",
+        "1",
+        "--argjson",
+        "b",
+        "2",
+        "$a, $b, $ARGS"
+    ],
+    "",
+    r#""1"
+2
+{"positional":[],"named":{"a":"1","b":2}}"#
+);
+
+test!(
+    args_positional,
+    &["-nc", "$ARGS.positional", "--args", "a", "b", "c"],
+    "",
+    r#"["a","b","c"]"#
+);
+
+test!(
+    jsonargs_positional,
+    &["-nc", "$ARGS.positional", "--jsonargs", "1", "{}"],
+    "",
+    "[1,{}]"
+);
+
+test!(
+    jsonargs,
+    &["-nc", "--jsonargs", "$ARGS.positional", "1", "{\"a\": 2}"],
+    "",
+    r#"[1,{"a":2}]"#
+);
+
 test!(
     compact,
     &["-c", "."],
@@ -52,6 +189,40 @@ test!(
 {"a":1,"b":["c"]}"#
 );
 
+test!(
+    sort_keys,
+    &["-cS", "."],
+    r#"{"b":{"d":1,"c":[{"f":2,"e":3}]},"a":3}"#,
+    r#"{"a":3,"b":{"c":[{"e":3,"f":2}],"d":1}}"#
+);
+
+// keys are sorted by Unicode codepoint, and only when printing
+test!(
+    sort_keys_unsorted,
+    &["-cS", "., keys_unsorted"],
+    r#"{"b":1,"ä":2,"B":3,"a":4}"#,
+    r#"{"B":3,"a":4,"b":1,"ä":2}
+["b","ä","B","a"]"#
+);
+
+test!(
+    ascii_output,
+    &["-ac", "., {(.): [.]}"],
+    r#""é😀""#,
+    r#""\u00e9\ud83d\ude00"
+{"\u00e9\ud83d\ude00":["\u00e9\ud83d\ude00"]}"#
+);
+
+// raw output is not escaped
+test!(ascii_raw_output, &["-ar"], r#""\u00e9\ud83d\ude00""#, "é😀");
+
+test!(
+    compact_nested,
+    &["-c", "."],
+    r#"{ "a" : [ 1 , 2 ] , "b" : { "c" : 3 , "d" : [ { } , [ ] ] } }"#,
+    r#"{"a":[1,2],"b":{"c":3,"d":[{},[]]}}"#
+);
+
 test!(
     inputs,
     &["-c", r#"{".": .}, {input: input}"#],
@@ -70,10 +241,67 @@ test!(
 {"inputs":[0,1,2,3]}"#
 );
 
+test!(null_input_add, &["-n", "[inputs] | add"], "1 2 3", "6");
+test!(
+    null_input_inputs,
+    &["-nc", "[inputs]"],
+    "1 [2]\n{}",
+    "[1,[2],{}]"
+);
+// values may follow each other without any whitespace in between
+test!(
+    null_input_concat,
+    &["-nc", "[inputs]"],
+    r#"1[2]{"a":3}"x"null"#,
+    r#"[1,[2],{"a":3},"x",null]"#
+);
+// filters starting with `.[]` yield the same outputs when
+// they are run on the elements of top-level arrays one by one
+test!(
+    iter_elements,
+    &["-c", ".[]?"],
+    r#"[1,[2]] {"a":3} 4 [] [5]"#,
+    "1\n[2]\n3\n5"
+);
+// ... unless they read inputs
+test!(
+    iter_input,
+    &["-c", ".[] | [., input]"],
+    "[1,2] [3] [4]",
+    "[1,[3]]\n[2,[4]]"
+);
+// the input is not parsed unless it is requested via `input` or `inputs`
+test!(null_input_unread, &["-n", "1"], "not JSON", "1");
+
+test!(
+    input_line_number,
+    &["-c", "[., input_line_number]"],
+    "1 2\n\n{\"a\":\n3}\n4",
+    "[1,1]\n[2,1]\n[{\"a\":3},4]\n[4,4]"
+);
+
 const ONE23: &str = "One\nTwo\nThree\n";
 
+test!(slurp, &["-s", "add"], "1 2 3", "6");
 test!(raw_input_slurp, &["-Rs"], ONE23, r#""One\nTwo\nThree\n""#);
 
+// the whole file is passed once to the filter, whereas `-R` passes every line
+test!(
+    raw_input_slurp_file,
+    &["-c", "-Rs", "[.]", "tests/data/lines.txt"],
+    "",
+    r#"["One\nTwo\n\nThree\n"]"#
+);
+test!(
+    raw_input_file,
+    &["-c", "-R", "[.]", "tests/data/lines.txt"],
+    "",
+    r#"["One"]
+["Two"]
+[""]
+["Three"]"#
+);
+
 test!(
     raw_input,
     &["-R"],
@@ -82,3 +310,380 @@ test!(
 "Two"
 "Three""#
 );
+
+// a trailing newline does not yield an empty string as last input
+test!(
+    raw_input_lines,
+    &["-cnR", "[inputs]"],
+    ONE23,
+    r#"["One","Two","Three"]"#
+);
+test!(
+    raw_input_no_newline,
+    &["-cnR", "[inputs]"],
+    "One\nTwo\nThree",
+    r#"["One","Two","Three"]"#
+);
+
+test!(
+    raw_output,
+    &["-r", r#".[], {a: .[0]}"#],
+    r#"["a\"b\tc", 1]"#,
+    "a\"b\tc\n1\n{\n  \"a\": \"a\\\"b\\tc\"\n}"
+);
+
+test!(
+    raw_output_nested,
+    &["-rc", ".[0], ."],
+    r#"["a", "b"]"#,
+    "a\n[\"a\",\"b\"]"
+);
+
+// `tostring` does not quote strings
+test!(
+    tostring,
+    &[".[] | tostring"],
+    r#"["hi", [1]]"#,
+    r#""hi"
+"[1]""#
+);
+test!(
+    tostring_raw,
+    &["-r", ".[] | tostring"],
+    r#"["hi", [1]]"#,
+    "hi\n[1]"
+);
+
+test!(
+    builtins,
+    &[
+        "-c",
+        r#"builtins | (. == sort), index("map/1") >= 0, index("@csv/0")"#
+    ],
+    "null",
+    "true\ntrue\nnull"
+);
+
+test!(
+    import,
+    &[
+        "-L",
+        "tests/mods",
+        r#"import "foo" as f; include "bar"; f::foo, bar"#
+    ],
+    "null",
+    "\"foobar\"\n\"bar\""
+);
+
+// undefined filters of imported modules are reported with the module alias
+#[test]
+fn import_undefined() -> io::Result<()> {
+    for (filter, name) in [("f::nope", "f::nope/0"), ("f::foo(1)", "f::foo/1")] {
+        let filter = format!(r#"import "foo" as f; {filter}"#);
+        let output = run(&["-L", "tests/mods", &filter], "null")?;
+        assert_eq!(output.status.code(), Some(3));
+        let stderr = str::from_utf8(&output.stderr).expect("invalid UTF-8 in output");
+        assert!(stderr.contains(&format!("undefined filter `{name}`")));
+    }
+    Ok(())
+}
+
+test!(
+    modulemeta,
+    &["-c", "-L", "tests/mods", "modulemeta"],
+    r#""foo""#,
+    r#"{"name":"foo","deps":[{"is_data":false,"relpath":"bar"}],"defs":["foo/0"]}"#
+);
+
+fn exit_code(args: &[&str], input: &str) -> io::Result<Option<i32>> {
+    Ok(run(args, input)?.status.code())
+}
+
+#[test]
+fn exit_status() -> io::Result<()> {
+    assert_eq!(exit_code(&["-e", "true"], "0")?, Some(0));
+    assert_eq!(exit_code(&["-e", ".[]"], "[true, null]")?, Some(1));
+    // errors keep their own exit code
+    assert_eq!(exit_code(&["-e", "1, error"], "0")?, Some(5));
+    // the last output over all files counts, even if later files yield nothing
+    let files = ["tests/data/1.json", "tests/data/2.json"];
+    assert_eq!(
+        exit_code(&["-e", "select(. == 1)", files[0], files[1]], "")?,
+        Some(0)
+    );
+    Ok(())
+}
+
+// the values of all files form a single stream of inputs
+#[test]
+fn files() -> io::Result<()> {
+    let (one, two) = ("tests/data/1.json", "tests/data/2.json");
+    golden_test(&["-nc", "[inputs]", one, two], "", "[1,2]")?;
+    let expected = format!("[1,\"{one}\"]\n[2,\"{two}\"]");
+    golden_test(&["-c", "[., input_filename]", one, two], "", &expected)?;
+    let expected = format!("[1,2,\"{two}\"]");
+    golden_test(
+        &["-c", "[., input, input_filename]", one, two],
+        "",
+        &expected,
+    )
+}
+
+// values of files before a file that cannot be opened are still processed
+#[test]
+fn files_missing() -> io::Result<()> {
+    let args = [
+        "tests/data/1.json",
+        "tests/data/missing.json",
+        "tests/data/2.json",
+    ];
+    let output = run(&[".", args[0], args[1], args[2]], "")?;
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(str::from_utf8(&output.stdout).unwrap().trim(), "1");
+    let stderr = str::from_utf8(&output.stderr).expect("invalid UTF-8 in output");
+    assert!(stderr.contains(args[1]));
+    Ok(())
+}
+
+// no output is distinguished from a falsy last output
+#[test]
+fn exit_status_no_output() -> io::Result<()> {
+    assert_eq!(exit_code(&["-e", "empty"], "0")?, Some(4));
+    assert_eq!(exit_code(&["-e", "false"], "0")?, Some(1));
+    assert_eq!(exit_code(&["-en", "empty"], "")?, Some(4));
+    Ok(())
+}
+
+test!(
+    loc,
+    &["-c", "1,\n$__loc__"],
+    "0",
+    r#"1
+{"file":"<top-level>","line":2}"#
+);
+
+test!(
+    loc_from_file,
+    &["-c", "-f", "tests/data/loc.jq"],
+    "0",
+    r#"{"file":"tests/data/loc.jq","line":4}"#
+);
+
+#[test]
+fn from_file_and_filter() -> io::Result<()> {
+    let output = run(&["-f", "tests/mods/foo.jq", ".[]"], "")?;
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = str::from_utf8(&output.stderr).expect("invalid UTF-8 in output");
+    assert!(stderr.contains("--from-file"));
+
+    // the filter is detected regardless of the following input files
+    let output = run(&["-f", "tests/data/loc.jq", ".a", "tests/data/1.json"], "")?;
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = str::from_utf8(&output.stderr).expect("invalid UTF-8 in output");
+    assert!(stderr.contains("cannot use filter `.a` together with `--from-file`"));
+
+    // input files are not mistaken for filters
+    golden_test(
+        &["-c", "-f", "tests/data/loc.jq", "tests/data/1.json"],
+        "",
+        r#"{"file":"tests/data/loc.jq","line":4}"#,
+    )?;
+
+    // missing input files are not mistaken for filters
+    let output = run(&["-f", "tests/data/loc.jq", "missing.json"], "")?;
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = str::from_utf8(&output.stderr).expect("invalid UTF-8 in output");
+    assert!(!stderr.contains("--from-file"));
+    assert!(stderr.contains("missing.json"));
+
+    // existing input files are not mistaken for filters, even if they compile
+    let output = process::Command::new(env!("CARGO_BIN_EXE_jaq"))
+        .args(["-c", "-f", "loc.jq", "1"])
+        .current_dir("tests/data")
+        .stdin(process::Stdio::null())
+        .output()?;
+    assert!(output.status.success());
+    let stdout = str::from_utf8(&output.stdout).expect("invalid UTF-8 in output");
+    let loc = r#"{"file":"loc.jq","line":4}"#;
+    assert_eq!(stdout.replace('\r', ""), format!("{loc}\n{loc}\n"));
+    Ok(())
+}
+
+test!(
+    recursion_limit,
+    &[
+        "--recursion-limit",
+        "3",
+        "def f: if . < 3 then 1 + (.+1 | f) else 0 end; f, (1 | f), (try (0 | f) catch .)"
+    ],
+    "1",
+    "2\n2\n\"recursion limit exceeded\""
+);
+
+// the default recursion limit and stack size suffice for ordinary recursion
+test!(
+    recursion_deep,
+    &[
+        "-c",
+        "def s: if length == 0 then 0 else .[0] + (.[1:] | s) end; [range(2000)] | s",
+    ],
+    "null",
+    "1999000"
+);
+test!(
+    recursion_deep_args,
+    &["def f(n): if n > 0 then f(n-1) else n end; f(1000)"],
+    "null",
+    "0"
+);
+test!(
+    recursion_deep_walk,
+    &["reduce range(1500) as $i (0; [.]) | walk(.) | tojson | length"],
+    "null",
+    "3001"
+);
+
+#[test]
+fn parse_depth() -> io::Result<()> {
+    let nested = |n| "[".repeat(n) + &"]".repeat(n);
+    golden_test(&["length"], &nested(10000), "1")?;
+
+    let output = run(&["length"], &nested(100000))?;
+    assert!(!output.status.success());
+    let stderr = str::from_utf8(&output.stderr).expect("invalid UTF-8 in output");
+    assert!(stderr.contains("maximal depth exceeded"));
+    Ok(())
+}
+
+#[test]
+fn newlines() -> io::Result<()> {
+    // golden tests ignore surrounding whitespace, so we check the exact output here
+    // (except for '\r', for compatibility with Windows)
+    let stdout = |args: &[&str]| {
+        let stdout = run(args, "0")?.stdout.into_iter();
+        Ok::<_, io::Error>(stdout.filter(|c| *c != b'\r').collect::<Vec<_>>())
+    };
+    assert_eq!(stdout(&["1, 2"])?, b"1\n2\n");
+    assert_eq!(stdout(&["-j", "1, 2"])?, b"12");
+    assert_eq!(stdout(&["-j", r#""a", "b""#])?, b"ab");
+    Ok(())
+}
+
+#[test]
+fn color_output() -> io::Result<()> {
+    let input = r#"{"b": [null], "a": "x"}"#;
+    let stdout = |args: &[&str], vars: &[(&str, &str)]| {
+        let output = run_env(args, vars, input)?;
+        Ok::<_, io::Error>(String::from_utf8(output.stdout).expect("invalid UTF-8 in output"))
+    };
+    let c = |s: &str| format!("\x1b[{s}m");
+    let (bold, key, green, reset) = (c("1"), c("1;34"), c("32"), c("0"));
+    let quote = |style: &str, s: &str| format!("{style}\"{reset}{style}{s}{reset}{style}\"{reset}");
+    let expected = format!(
+        "{bold}{{{reset}{}:{},{}:{bold}[{reset}null{bold}]{reset}{bold}}}{reset}\n",
+        quote(&key, "a"),
+        quote(&green, "x"),
+        quote(&key, "b"),
+    );
+    assert_eq!(stdout(&["-CcS", "."], &[])?, expected);
+    // `-C` takes precedence over `NO_COLOR`, and `-M` disables colors
+    assert_eq!(stdout(&["-CcS", "."], &[("NO_COLOR", "1")])?, expected);
+    assert_eq!(stdout(&["-McS", "."], &[])?, "{\"a\":\"x\",\"b\":[null]}\n");
+
+    let colors = [("JQ_COLORS", "0;31:0;32:0;33:0;34:0;35:0;36:0;37:1;31")];
+    let expected = format!("{}null{reset}\n", c("31"));
+    assert_eq!(stdout(&["-C", ".b[0]"], &colors)?, expected);
+    Ok(())
+}
+
+#[test]
+fn flush() -> io::Result<()> {
+    use io::{Read, Write};
+    // the first value must be written before jaq waits for more input
+    let mut child = process::Command::new(env!("CARGO_BIN_EXE_jaq"))
+        .args(["-nj", r#""a", input"#])
+        .stdin(process::Stdio::piped())
+        .stdout(process::Stdio::piped())
+        .spawn()?;
+    let mut stdout = child.stdout.take().unwrap();
+    let mut a = [0];
+    stdout.read_exact(&mut a)?;
+    assert_eq!(&a, b"a");
+
+    child.stdin.take().unwrap().write_all(br#""b""#)?;
+    let mut rest = String::new();
+    stdout.read_to_string(&mut rest)?;
+    assert_eq!(rest, "b");
+    assert!(child.wait()?.success());
+    Ok(())
+}
+
+// simd-json parses only complete lines, so it waits for the end of the line here
+#[cfg(not(feature = "simd-json"))]
+#[test]
+fn incremental() -> io::Result<()> {
+    use io::{BufRead, Read, Write};
+    // every value is output before jaq reads the next value,
+    // when using `--stream`, every event is output before jaq reads the next event, and
+    // when the filter starts with `.[]`, every element is output before jaq reads the next element
+    let tests: [(&[&str], _, _); 3] = [
+        (&["-c", "."], "1\n", "[2,3]\n"),
+        (
+            &["-c", "--stream", "."],
+            "[[],1]\n",
+            "[[0],2]\n[[1],3]\n[[1]]\n",
+        ),
+        (&["-c", ".[]?"], "2\n", "3\n"),
+    ];
+    for (args, first, rest) in tests {
+        let mut child = process::Command::new(env!("CARGO_BIN_EXE_jaq"))
+            .args(args)
+            .stdin(process::Stdio::piped())
+            .stdout(process::Stdio::piped())
+            .spawn()?;
+        let mut stdin = child.stdin.take().unwrap();
+        let mut stdout = io::BufReader::new(child.stdout.take().unwrap());
+        stdin.write_all(b"1 [2,")?;
+        let mut line = String::new();
+        stdout.read_line(&mut line)?;
+        assert_eq!(line, first);
+
+        stdin.write_all(b"3]")?;
+        drop(stdin);
+        line.clear();
+        stdout.read_to_string(&mut line)?;
+        assert_eq!(line, rest);
+        assert!(child.wait()?.success());
+    }
+    Ok(())
+}
+
+#[test]
+fn seq() -> io::Result<()> {
+    // the malformed record `[2,` is skipped
+    let output = run(&["-c", "--seq", "."], "\x1e1\n\x1e[2,\n\x1e3 [4]\n")?;
+    let stdout: Vec<_> = output.stdout.into_iter().filter(|c| *c != b'\r').collect();
+    assert_eq!(stdout, b"\x1e1\n\x1e3\n\x1e[4]\n");
+    let stderr = str::from_utf8(&output.stderr).expect("invalid UTF-8 in output");
+    assert!(stderr.contains("ignoring parse error"));
+    Ok(())
+}
+
+test!(
+    stream,
+    &["-c", "--stream"],
+    r#"{"a": [1, {"b": 2}]} 3"#,
+    r#"[["a",0],1]
+[["a",1,"b"],2]
+[["a",1,"b"]]
+[["a",1]]
+[["a"]]
+[[],3]"#
+);
+
+test!(
+    stream_fromstream,
+    &["-c", "--stream", "-n", "[fromstream(inputs)]"],
+    r#"{"a": [1, {"b": 2}]} [] 3"#,
+    r#"[{"a":[1,{"b":2}]},[],3]"#
+);